@@ -0,0 +1,65 @@
+//! Log the traffic of a port as timestamped hex dumps.
+//!
+//! Usage: `cargo run --example hex_trace -- <PATH> <BAUD_RATE> [MESSAGE]`
+//!
+//! This sends `MESSAGE`, if given, and then logs what is received until
+//! interrupted.
+
+#[cfg(not(target_os = "windows"))]
+fn main() -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut args = std::env::args().skip(1);
+    let (Some(path), Some(baud_rate)) = (args.next(), args.next()) else {
+        eprintln!("usage: hex_trace <PATH> <BAUD_RATE> [MESSAGE]");
+        std::process::exit(2);
+    };
+    let baud_rate = baud_rate
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let message = args.next();
+
+    let start = std::time::Instant::now();
+    let builder = kioto_serial::new(path, baud_rate).with_tracer(move |direction, buf| {
+        let elapsed = start.elapsed().as_secs_f64();
+        let arrow = match direction {
+            kioto_serial::Direction::Read => "<-",
+            kioto_serial::Direction::Write => "->",
+        };
+        for (i, line) in buf.chunks(16).enumerate() {
+            let hex: Vec<String> = line.iter().map(|byte| format!("{byte:02x}")).collect();
+            let text: String = line
+                .iter()
+                .map(|&byte| match byte {
+                    0x20..=0x7e => byte as char,
+                    _ => '.',
+                })
+                .collect();
+            println!(
+                "{elapsed:12.6} {arrow} {:04x}  {:<47}  {text}",
+                i * 16,
+                hex.join(" ")
+            );
+        }
+    });
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let mut stream = kioto_serial::SerialPortBuilderExt::open_native_async(builder)?;
+            if let Some(message) = message {
+                stream.write_all(message.as_bytes()).await?;
+                stream.flush().await?;
+            }
+            // The tracer logs what arrives.
+            let mut buf = [0u8; 1024];
+            while stream.read(&mut buf).await? > 0 {}
+            Ok(())
+        })
+}
+
+#[cfg(target_os = "windows")]
+fn main() {
+    eprintln!("tracing is not available on Windows");
+}
@@ -0,0 +1,89 @@
+//! Measure the CPU time spent receiving a steady stream of data.
+//!
+//! Usage: `cargo run --release --example read_throughput [SECONDS]`
+//!
+//! This sends 1 MiB/s through a pseudo-terminal for `SECONDS`, 5 by default,
+//! and reads it from a stream. Run it with and without `--features bytes` to
+//! compare how the reader thread hands on data. The CPU time includes the
+//! thread sending the data, which is the same either way.
+
+#[cfg(not(target_os = "windows"))]
+fn main() -> std::io::Result<()> {
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+
+    use serialport::SerialPort;
+    use tokio::io::AsyncReadExt;
+
+    const RATE: usize = 1024 * 1024;
+    const CHUNK: usize = 1024;
+
+    let seconds: u64 = match std::env::args().nth(1) {
+        Some(seconds) => seconds
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+        None => 5,
+    };
+    let total = RATE * seconds as usize;
+
+    let (mut master, slave) = serialport::TTYPort::pair()?;
+    let path = slave.name().expect("a pseudo-terminal has a name");
+    drop(slave);
+    let builder = kioto_serial::new(path, 115_200);
+    let mut stream = kioto_serial::SerialPortBuilderExt::open_native_async(builder)?;
+
+    let start = Instant::now();
+    let cpu_start = cpu_time();
+    let sender = std::thread::spawn(move || -> std::io::Result<serialport::TTYPort> {
+        let chunk = [0x55u8; CHUNK];
+        for i in 0..total / CHUNK {
+            // Keep to the rate on average rather than sleeping a fixed time.
+            let due = start + Duration::from_secs_f64((i * CHUNK) as f64 / RATE as f64);
+            std::thread::sleep(due.saturating_duration_since(Instant::now()));
+            master.write_all(&chunk)?;
+        }
+        // Closing the other end now would hang up the stream.
+        Ok(master)
+    });
+
+    let received = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let mut buf = vec![0u8; 4096];
+            let mut received = 0;
+            while received < total {
+                received += stream.read(&mut buf).await?;
+            }
+            Ok::<_, std::io::Error>(received)
+        })?;
+    let elapsed = start.elapsed();
+    let cpu = cpu_time() - cpu_start;
+    let _master = sender.join().expect("the sending thread panicked")?;
+
+    println!(
+        "received {received} bytes in {:.2} s using {:.3} s of CPU time ({:.1}%), bytes feature {}",
+        elapsed.as_secs_f64(),
+        cpu.as_secs_f64(),
+        100.0 * cpu.as_secs_f64() / elapsed.as_secs_f64(),
+        if cfg!(feature = "bytes") { "on" } else { "off" },
+    );
+    Ok(())
+}
+
+/// The CPU time used by this process so far.
+#[cfg(not(target_os = "windows"))]
+fn cpu_time() -> std::time::Duration {
+    let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+    // SAFETY: `usage` is valid for writing.
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) };
+    let to_duration = |time: libc::timeval| {
+        std::time::Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+    };
+    to_duration(usage.ru_utime) + to_duration(usage.ru_stime)
+}
+
+#[cfg(target_os = "windows")]
+fn main() {
+    eprintln!("this benchmark is not available on Windows");
+}
@@ -0,0 +1,175 @@
+//! Framing of packets with Consistent Overhead Byte Stuffing (COBS).
+
+use tokio_util::bytes::{BufMut, BytesMut};
+
+/// A [tokio_util::codec::Decoder] and [tokio_util::codec::Encoder] for packets
+/// encoded with Consistent Overhead Byte Stuffing (COBS).
+///
+/// Each packet is COBS-encoded and followed by a zero byte as delimiter. This
+/// can be used with [tokio_util::codec::Framed] on a [crate::SerialStream] or
+/// through [crate::SerialStream::cobs_frames].
+///
+/// When decoding, empty frames, as produced by consecutive delimiters, are
+/// skipped. A frame which is not valid COBS or decodes to more than the maximum
+/// length is reported as an error of kind [std::io::ErrorKind::InvalidData].
+/// Decoding then continues with the next frame, so that a corrupt frame does
+/// not affect the following ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CobsCodec {
+    max_length: usize,
+    /// Where to continue looking for a delimiter.
+    next_index: usize,
+    /// Whether the rest of an over-long frame is being skipped.
+    discarding: bool,
+}
+
+impl CobsCodec {
+    /// Create a codec for packets of any length.
+    pub fn new() -> Self {
+        Self::new_with_max_length(usize::MAX)
+    }
+
+    /// Create a codec for packets of at most `max_length` bytes.
+    ///
+    /// This bounds how much data is buffered while waiting for a delimiter.
+    pub fn new_with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length,
+            next_index: 0,
+            discarding: false,
+        }
+    }
+
+    /// Get the maximum length of a packet.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// The longest encoding of a packet of the maximum length, without the
+    /// delimiter.
+    fn max_encoded_length(&self) -> usize {
+        self.max_length
+            .saturating_add(self.max_length / 254)
+            .saturating_add(1)
+    }
+}
+
+impl Default for CobsCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl tokio_util::codec::Decoder for CobsCodec {
+    type Item = Vec<u8>;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Vec<u8>>, std::io::Error> {
+        loop {
+            let Some(end) = buf[self.next_index..].iter().position(|b| *b == 0) else {
+                if buf.len() > self.max_encoded_length() {
+                    buf.clear();
+                    self.next_index = 0;
+                    if !self.discarding {
+                        self.discarding = true;
+                        return Err(too_long());
+                    }
+                } else {
+                    self.next_index = buf.len();
+                }
+                return Ok(None);
+            };
+            let frame = buf.split_to(self.next_index + end + 1);
+            self.next_index = 0;
+            if std::mem::take(&mut self.discarding) {
+                continue;
+            }
+            let frame = &frame[..frame.len() - 1];
+            if frame.is_empty() {
+                continue;
+            }
+            if frame.len() > self.max_encoded_length() {
+                return Err(too_long());
+            }
+            return match decode(frame) {
+                Some(packet) if packet.len() <= self.max_length => Ok(Some(packet)),
+                Some(_) => Err(too_long()),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "corrupt COBS frame",
+                )),
+            };
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Vec<u8>>, std::io::Error> {
+        // A frame without delimiter is incomplete and dropped.
+        let packet = self.decode(buf)?;
+        if packet.is_none() {
+            buf.clear();
+            self.next_index = 0;
+        }
+        Ok(packet)
+    }
+}
+
+impl<T: AsRef<[u8]>> tokio_util::codec::Encoder<T> for CobsCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, packet: T, buf: &mut BytesMut) -> Result<(), std::io::Error> {
+        let packet = packet.as_ref();
+        if packet.len() > self.max_length {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "COBS packet longer than the maximum length",
+            ));
+        }
+        buf.reserve(packet.len() + packet.len() / 254 + 2);
+        let mut code_index = buf.len();
+        buf.put_u8(0);
+        let mut code = 1u8;
+        for (i, byte) in packet.iter().enumerate() {
+            if *byte != 0 {
+                buf.put_u8(*byte);
+                code += 1;
+            }
+            // A full block needs no new one if it ends the packet.
+            if *byte == 0 || (code == 0xFF && i + 1 < packet.len()) {
+                buf[code_index] = code;
+                code_index = buf.len();
+                buf.put_u8(0);
+                code = 1;
+            }
+        }
+        buf[code_index] = code;
+        buf.put_u8(0);
+        Ok(())
+    }
+}
+
+/// Decode a COBS frame without its delimiter, or return `None` if it is not
+/// valid.
+fn decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut packet = Vec::with_capacity(frame.len());
+    let mut index = 0;
+    while index < frame.len() {
+        let code = usize::from(frame[index]);
+        let end = index + code;
+        if end > frame.len() {
+            return None;
+        }
+        packet.extend_from_slice(&frame[index + 1..end]);
+        index = end;
+        if code < 0xFF && index < frame.len() {
+            packet.push(0);
+        }
+    }
+    Some(packet)
+}
+
+fn too_long() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "COBS frame longer than the maximum length",
+    )
+}
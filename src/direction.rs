@@ -0,0 +1,51 @@
+//! Which directions a stream is opened for, and which way data flows.
+
+/// Which directions a stream transfers data in, set with
+/// `SerialPortBuilder::direction`.
+///
+/// A stream opened for one direction only does not start the worker thread
+/// for the other, nor clone the port handle for it, which saves resources
+/// when, for example, tapping many ports for logging. Reading from a
+/// write-only stream or writing to a read-only one fails with
+/// [std::io::ErrorKind::Unsupported].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StreamDirection {
+    /// Read and write.
+    #[default]
+    ReadWrite,
+    /// Only read.
+    ReadOnly,
+    /// Only write.
+    WriteOnly,
+}
+
+impl StreamDirection {
+    /// Whether the stream reads.
+    pub fn reads(self) -> bool {
+        self != StreamDirection::WriteOnly
+    }
+
+    /// Whether the stream writes.
+    pub fn writes(self) -> bool {
+        self != StreamDirection::ReadOnly
+    }
+}
+
+/// Which worker thread of a `SerialStream` something relates to, or which way
+/// bytes pass, as given to the tracer set with `SerialPortBuilder::with_tracer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// The thread reading from the port, or received bytes.
+    Read,
+    /// The thread writing to the port, or transmitted bytes.
+    Write,
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Read => f.write_str("reader"),
+            Direction::Write => f.write_str("writer"),
+        }
+    }
+}
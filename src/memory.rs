@@ -0,0 +1,293 @@
+//! An in-memory serial port, used for loopback mode and by
+//! `SerialStream::pair`.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::SerialPortSettings;
+
+/// One end of a pair of in-memory ports connected back to back, or a port
+/// connected to itself.
+///
+/// Bytes written to one end are immediately readable on the other. The
+/// settings of each end are independent, like those of two real ports, and the
+/// control lines are wired as by a null modem cable: RTS drives the CTS of the
+/// other end, and DTR drives its DSR and CD.
+///
+/// Clones made with [SerialPort::try_clone] share the end they were made from.
+/// Once all handles of one end are dropped, reading and writing the other end
+/// fails with [std::io::ErrorKind::BrokenPipe], as with a hung up tty.
+pub(crate) struct MemoryPort {
+    shared: Arc<Shared>,
+    end: usize,
+    /// The end this one is connected to, which is `end` itself for a port in
+    /// loopback.
+    peer: usize,
+    timeout: Duration,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn new() -> Arc<Shared> {
+        Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    ends: [End; 2],
+}
+
+struct End {
+    /// Bytes written by the other end and not read yet.
+    incoming: VecDeque<u8>,
+    /// The number of open handles of this end.
+    handles: usize,
+    settings: SerialPortSettings,
+    request_to_send: bool,
+    data_terminal_ready: bool,
+}
+
+impl Default for End {
+    fn default() -> Self {
+        Self {
+            incoming: VecDeque::new(),
+            handles: 1,
+            settings: SerialPortSettings {
+                baud_rate: 9600,
+                data_bits: DataBits::Eight,
+                parity: Parity::None,
+                stop_bits: StopBits::One,
+                flow_control: FlowControl::None,
+            },
+            request_to_send: false,
+            data_terminal_ready: false,
+        }
+    }
+}
+
+impl MemoryPort {
+    /// Create two connected ends.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn pair() -> (MemoryPort, MemoryPort) {
+        let shared = Shared::new();
+        let end = |end| MemoryPort {
+            shared: shared.clone(),
+            end,
+            peer: 1 - end,
+            timeout: Duration::ZERO,
+        };
+        (end(0), end(1))
+    }
+
+    /// Create a port which reads back what is written to it.
+    pub(crate) fn looped() -> MemoryPort {
+        MemoryPort {
+            shared: Shared::new(),
+            end: 0,
+            peer: 0,
+            timeout: Duration::ZERO,
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        crate::posix::lock(&self.shared.state)
+    }
+
+    fn with_end<T>(&self, f: impl FnOnce(&mut End) -> T) -> serialport::Result<T> {
+        Ok(f(&mut self.state().ends[self.end]))
+    }
+
+    /// Get a value of the other end.
+    fn with_peer<T>(&self, f: impl FnOnce(&End) -> T) -> serialport::Result<T> {
+        Ok(f(&self.state().ends[self.peer]))
+    }
+}
+
+impl Drop for MemoryPort {
+    fn drop(&mut self) {
+        self.state().ends[self.end].handles -= 1;
+        self.shared.changed.notify_all();
+    }
+}
+
+fn disconnected() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "other end closed")
+}
+
+impl std::io::Read for MemoryPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let deadline = Instant::now().checked_add(self.timeout);
+        let mut state = self.state();
+        loop {
+            let End { incoming, .. } = &mut state.ends[self.end];
+            if !incoming.is_empty() {
+                let sz = buf.len().min(incoming.len());
+                for (dst, src) in buf.iter_mut().zip(incoming.drain(..sz)) {
+                    *dst = src;
+                }
+                return Ok(sz);
+            }
+            if state.ends[self.peer].handles == 0 {
+                return Err(disconnected());
+            }
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            if remaining.is_zero() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "operation timed out",
+                ));
+            }
+            state = self
+                .shared
+                .changed
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+        }
+    }
+}
+
+impl std::io::Write for MemoryPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.state();
+        let peer = &mut state.ends[self.peer];
+        if peer.handles == 0 {
+            return Err(disconnected());
+        }
+        peer.incoming.extend(buf);
+        drop(state);
+        self.shared.changed.notify_all();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for MemoryPort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.with_end(|end| end.settings.baud_rate)
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.with_end(|end| end.settings.data_bits)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.with_end(|end| end.settings.flow_control)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.with_end(|end| end.settings.parity)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.with_end(|end| end.settings.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.with_end(|end| end.settings.baud_rate = baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.with_end(|end| end.settings.data_bits = data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.with_end(|end| end.settings.flow_control = flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.with_end(|end| end.settings.parity = parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.with_end(|end| end.settings.stop_bits = stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.with_end(|end| end.request_to_send = level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.with_end(|end| end.data_terminal_ready = level)
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.with_peer(|peer| peer.request_to_send)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.with_peer(|peer| peer.data_terminal_ready)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.with_peer(|peer| peer.data_terminal_ready)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.with_end(|end| end.incoming.len().try_into().unwrap_or(u32::MAX))
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        // Written bytes are handed to the other end immediately.
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        if matches!(buffer_to_clear, ClearBuffer::Input | ClearBuffer::All) {
+            self.with_end(|end| end.incoming.clear())?;
+        }
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        self.with_end(|end| end.handles += 1)?;
+        Ok(Box::new(MemoryPort {
+            shared: self.shared.clone(),
+            end: self.end,
+            peer: self.peer,
+            timeout: self.timeout,
+        }))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! A stream fed from a script of received bytes, for testing.
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::memory::MemoryPort;
+use crate::SerialStream;
+
+/// Builder for a [SerialStream] which receives scripted bytes.
+///
+/// Each entry of the schedule holds a delay and the bytes which arrive once
+/// it has passed. Delays count from the arrival of the previous entry, or
+/// from opening the stream for the first one, and are measured in real time,
+/// so tokio's paused test clock does not affect them. Once the schedule is
+/// exhausted, the line stays idle. Bytes written to the stream are collected
+/// in the [MockHandle] returned along with it.
+///
+/// Like the streams of [SerialStream::pair], the stream runs worker threads on
+/// top of an in-memory port, so it behaves like one opened from a device, and
+/// it cannot be reopened. This requires the `test-util` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MockSerial {
+    schedule: Vec<(Duration, Vec<u8>)>,
+}
+
+impl MockSerial {
+    /// Create a builder delivering `schedule`.
+    pub fn new(schedule: Vec<(Duration, Vec<u8>)>) -> Self {
+        Self { schedule }
+    }
+
+    /// Append `bytes` to the schedule, arriving `delay` after the previous
+    /// entry.
+    pub fn then(mut self, delay: Duration, bytes: impl Into<Vec<u8>>) -> Self {
+        self.schedule.push((delay, bytes.into()));
+        self
+    }
+
+    /// Open the stream and start delivering the schedule.
+    ///
+    /// The schedule is run by a thread of its own, which exits once it is
+    /// done or the stream was dropped.
+    pub fn open(self) -> std::io::Result<(SerialStream, MockHandle)> {
+        let (port, mut feeder) = MemoryPort::pair();
+        let handle = MockHandle {
+            port: feeder.try_clone()?,
+        };
+        let stream = SerialStream::from_serialport(Box::new(port), 1024)?;
+        std::thread::Builder::new()
+            .name("kioto-serial-mock".into())
+            .spawn(move || {
+                for (delay, bytes) in self.schedule {
+                    std::thread::sleep(delay);
+                    // Writing fails once the stream was dropped.
+                    if feeder.write_all(&bytes).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        Ok((stream, handle))
+    }
+}
+
+/// The other end of a stream opened with [MockSerial::open].
+///
+/// Dropping this does not affect the stream.
+pub struct MockHandle {
+    port: Box<dyn SerialPort>,
+}
+
+impl MockHandle {
+    /// Take the bytes written to the stream so far.
+    ///
+    /// Bytes written to the stream are handed on by its writer thread, so
+    /// flush the stream first to make sure that all of them are included.
+    pub fn take_written(&mut self) -> std::io::Result<Vec<u8>> {
+        let mut written = vec![0; self.port.bytes_to_read()? as usize];
+        if !written.is_empty() {
+            let sz = self.port.read(&mut written)?;
+            written.truncate(sz);
+        }
+        Ok(written)
+    }
+
+    /// Get the level of the RTS line set on the stream.
+    pub fn request_to_send(&mut self) -> std::io::Result<bool> {
+        Ok(self.port.read_clear_to_send()?)
+    }
+
+    /// Get the level of the DTR line set on the stream.
+    pub fn data_terminal_ready(&mut self) -> std::io::Result<bool> {
+        Ok(self.port.read_data_set_ready()?)
+    }
+}
+
+impl std::fmt::Debug for MockHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockHandle").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,49 @@
+//! What a stream does in situations which have more than one sensible outcome.
+
+/// What writing to a `SerialStream` does when the internal buffer is full,
+/// set with `SerialPortBuilder::write_policy`.
+///
+/// The internal buffer holds up to the write buffer size of the stream. It fills
+/// up when the program writes faster than the port transmits. On Windows,
+/// where `tokio-serial` has no internal buffer, only `Block` is supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WritePolicy {
+    /// Wait until the writer thread has made room.
+    ///
+    /// This applies backpressure: a slow port slows down the writing task, and
+    /// no bytes are lost.
+    #[default]
+    Block,
+    /// Discard the oldest queued bytes to make room.
+    ///
+    /// Writing never waits for the port, which suits lossy telemetry where only
+    /// recent data matters. Dropped bytes are not reported, and messages may be
+    /// cut anywhere, so that the peer sees partial messages. Bytes which
+    /// the writer thread has already handed to the port are never dropped.
+    DropOldest,
+}
+
+/// What the reader thread of a `SerialStream` does when reading from the port
+/// returns no bytes, set with `SerialPortBuilder::empty_read_policy`.
+///
+/// Ports normally report a lack of data as a timeout, which the reader thread
+/// handles separately. Some drivers and pseudo-terminals, however, return
+/// zero bytes instead, either spuriously or because the other side went away.
+/// On Windows, where `tokio-serial` has no reader thread, only `Retry` is
+/// supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmptyReadPolicy {
+    /// Wait briefly and read again.
+    ///
+    /// The wait keeps a port which keeps returning zero bytes from using up a
+    /// CPU core, at the cost of a few milliseconds of latency after each such
+    /// read.
+    #[default]
+    Retry,
+    /// Treat it as the end of the input and stop the reader thread.
+    ///
+    /// Reading from the stream then reports the end of the stream after the
+    /// bytes received before, as for a closed stream. Writing is not
+    /// affected.
+    Eof,
+}
@@ -1,169 +1,3713 @@
 use std::{
     future::Future,
+    os::unix::io::AsRawFd,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
-use futures::{future::FutureExt, stream::StreamExt};
+use futures::{
+    future::{Either, FutureExt},
+    StreamExt,
+};
 use pin_project_lite::pin_project;
-use serialport::SerialPort;
-use tokio::io::{AsyncWriteExt, ReadBuf};
+use tokio::io::{AsyncReadExt, ReadBuf};
+
+use crate::{Direction, EmptyReadPolicy, WritePolicy};
+
+pub use serialport::{
+    ClearBuffer, DataBits, FlowControl, Parity, SerialPort, SerialPortInfo, SerialPortType,
+    StopBits, UsbPortInfo,
+};
+
+// ensure that we never instantiate a NeverOk type
+macro_rules! assert_never {
+    ($never: expr) => {{
+        let _: NeverOk = $never;
+        unreachable!("NeverOk was instantiated");
+    }};
+}
+
+/// Timeout used to emulate fully blocking I/O.
+const FOREVER: std::time::Duration = std::time::Duration::from_secs(60 * 60 * 24 * 365 * 100);
+
+/// Size of the buffer the writer thread moves bytes to the port with.
+const WRITE_CHUNK_SIZE: usize = 4096;
+
+/// Most bytes [tokio::io::AsyncBufRead::poll_fill_buf] takes from the internal
+/// buffer at once.
+const FILL_BUF_SIZE: usize = 1024;
+
+/// Longest time the reader thread blocks on the port before checking whether
+/// it should stop.
+const READ_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// How long the reader thread waits before reading again after reading from
+/// the port returned no bytes, see [EmptyReadPolicy::Retry].
+const EMPTY_READ_BACKOFF: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// How often [SerialStream::ready] checks whether the device is live.
+const READY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// How far, in percent, the baud rate of an opened port may be off from the
+/// requested one. This is about what a UART tolerates.
+const BAUD_RATE_TOLERANCE_PERCENT: u64 = 3;
+
+/// Builder to open a serial port.
+///
+/// Create this by calling [new]. Open the port by calling
+/// [SerialPortBuilderExt::open_native_async].
+#[derive(Debug, Clone)]
+pub struct SerialPortBuilder {
+    path: String,
+    baud_rate: u32,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+    flow_control: FlowControl,
+    exclusive: bool,
+    dtr_on_open: Option<bool>,
+    rts_on_open: Option<bool>,
+    clear_on_open: Option<ClearBuffer>,
+    force_raw_mode: bool,
+    options: StreamOptions,
+}
+
+/// Settings of a [SerialStream] which do not configure the port itself.
+#[derive(Debug, Clone)]
+struct StreamOptions {
+    max_buf_size: usize,
+    /// Override `max_buf_size` for one direction.
+    read_buf_size: Option<usize>,
+    write_buf_size: Option<usize>,
+    timeout: Option<std::time::Duration>,
+    read_idle_timeout: Option<std::time::Duration>,
+    write_timeout: Option<std::time::Duration>,
+    write_policy: WritePolicy,
+    max_inflight_write: Option<usize>,
+    unbounded_write_buffer: bool,
+    empty_read_policy: EmptyReadPolicy,
+    nonblocking_io: bool,
+    read_chunk_size: usize,
+    read_coalesce: Option<(std::time::Duration, usize)>,
+    write_chunk_size: usize,
+    write_chunk_pause: std::time::Duration,
+    report_input_errors: bool,
+    disconnect_as_eof: bool,
+    direction: crate::StreamDirection,
+    loopback: bool,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+    rs485: Option<crate::Rs485Config>,
+    /// Shared by the clones of a stream and kept when reopening.
+    counters: Option<Arc<SerialCounters>>,
+    spawner: Option<ThreadSpawner>,
+    tracer: Option<Tracer>,
+}
+
+impl StreamOptions {
+    fn read_buf_size(&self) -> usize {
+        self.read_buf_size.unwrap_or(self.max_buf_size)
+    }
+
+    fn write_buf_size(&self) -> usize {
+        self.write_buf_size.unwrap_or(self.max_buf_size)
+    }
+
+    /// Check the settings which would make opening a stream fail.
+    fn validate(&self) -> std::io::Result<()> {
+        if self.read_buf_size() == 0 || self.write_buf_size() == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "buffer sizes must be at least 1",
+            ));
+        }
+        if self.max_inflight_write == Some(0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the limit of bytes in flight must be at least 1",
+            ));
+        }
+        let coalesce_size = match self.read_coalesce {
+            Some((_, size)) if size == 0 || size > self.read_buf_size() => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "the coalescing size must be between 1 and the read buffer size",
+                ));
+            }
+            Some((_, size)) => size,
+            None => 1,
+        };
+        // The reader thread reads on top of fewer than `coalesce_size` bytes.
+        if self
+            .read_chunk_size
+            .max(1)
+            .checked_add(coalesce_size - 1)
+            .is_none()
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the read chunk size is too large",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            max_buf_size: 1024,
+            read_buf_size: None,
+            write_buf_size: None,
+            timeout: None,
+            read_idle_timeout: None,
+            write_timeout: None,
+            write_policy: WritePolicy::Block,
+            max_inflight_write: None,
+            unbounded_write_buffer: false,
+            empty_read_policy: EmptyReadPolicy::Retry,
+            nonblocking_io: false,
+            read_chunk_size: 1024,
+            read_coalesce: None,
+            write_chunk_size: usize::MAX,
+            write_chunk_pause: std::time::Duration::ZERO,
+            report_input_errors: false,
+            disconnect_as_eof: false,
+            direction: crate::StreamDirection::ReadWrite,
+            loopback: false,
+            cancel: None,
+            rs485: None,
+            counters: None,
+            spawner: None,
+            tracer: None,
+        }
+    }
+}
+
+/// Create a [SerialPortBuilder] from a device path and a baud rate.
+///
+/// Non-standard baud rates, such as 250000 for DMX512, are supported where the
+/// platform allows them. On Linux, a rate which the port does not take from
+/// the table of standard rates is set with `BOTHER`, which passes any rate to
+/// the driver. If the rate the port ends up with differs from `baud_rate` by
+/// more than a few percent, [SerialPortBuilderExt::open_native_async] fails
+/// with [std::io::ErrorKind::InvalidInput].
+pub fn new<'a>(path: impl Into<std::borrow::Cow<'a, str>>, baud_rate: u32) -> SerialPortBuilder {
+    SerialPortBuilder {
+        path: path.into().into_owned(),
+        baud_rate,
+        data_bits: DataBits::Eight,
+        parity: Parity::None,
+        stop_bits: StopBits::One,
+        flow_control: FlowControl::None,
+        exclusive: true,
+        dtr_on_open: None,
+        rts_on_open: None,
+        clear_on_open: None,
+        force_raw_mode: false,
+        options: StreamOptions::default(),
+    }
+}
+
+/// Create a [SerialPortBuilder] for the USB serial device with the given
+/// vendor and product ID.
+///
+/// Device paths like `/dev/ttyUSB0` can change between reboots or when devices
+/// are plugged into different ports. This looks up the path of the device
+/// instead, with [serialport::available_ports]. Enumerating ports involves
+/// blocking system calls, so avoid calling this from an async task.
+///
+/// If no device matches, this fails with [std::io::ErrorKind::NotFound]. If
+/// several devices match, e.g. two adapters of the same model, this fails with
+/// [std::io::ErrorKind::InvalidInput], as the device to use is ambiguous. On
+/// macOS, only the `/dev/cu.*` device of each port is considered.
+pub fn new_by_usb(vid: u16, pid: u16, baud_rate: u32) -> std::io::Result<SerialPortBuilder> {
+    let paths: Vec<String> = serialport::available_ports()?
+        .into_iter()
+        .filter(|port| {
+            matches!(&port.port_type, SerialPortType::UsbPort(usb) if usb.vid == vid && usb.pid == pid)
+        })
+        .map(|port| port.port_name)
+        .filter(|path| !cfg!(target_os = "macos") || !path.starts_with("/dev/tty."))
+        .collect();
+    match paths.as_slice() {
+        [path] => Ok(new(path, baud_rate)),
+        [] => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no USB serial device with ID {vid:04x}:{pid:04x}"),
+        )),
+        paths => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "several USB serial devices with ID {vid:04x}:{pid:04x}: {}",
+                paths.join(", ")
+            ),
+        )),
+    }
+}
+
+/// Look up the USB device information of the port at `path`.
+///
+/// Returns `None` if the port is not a USB device or enumerating the ports
+/// fails. Paths are compared after resolving symbolic links, so that e.g.
+/// `/dev/serial/by-id` paths are found as well.
+fn find_usb_info(path: &str) -> Option<UsbPortInfo> {
+    let path = std::fs::canonicalize(path).ok()?;
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find_map(|port| match port.port_type {
+            SerialPortType::UsbPort(usb)
+                if std::fs::canonicalize(&port.port_name).is_ok_and(|name| name == path) =>
+            {
+                Some(usb)
+            }
+            _ => None,
+        })
+}
+
+/// List the serial ports available on the system.
+///
+/// Enumerating ports involves blocking system calls, so this runs
+/// [serialport::available_ports] on tokio's blocking thread pool. It must be
+/// called from within a tokio runtime.
+pub async fn available_ports() -> std::io::Result<Vec<SerialPortInfo>> {
+    tokio::task::spawn_blocking(serialport::available_ports)
+        .await
+        .map_err(std::io::Error::other)?
+        .map_err(Into::into)
+}
+
+impl SerialPortBuilder {
+    /// Set the number of bits used to represent a character sent on the line.
+    ///
+    /// Defaults to [DataBits::Eight].
+    pub fn data_bits(self, data_bits: DataBits) -> Self {
+        Self { data_bits, ..self }
+    }
+
+    /// Set the type of parity to use for error checking.
+    ///
+    /// Defaults to [Parity::None].
+    pub fn parity(self, parity: Parity) -> Self {
+        Self { parity, ..self }
+    }
+
+    /// Set the number of bits to use to signal the end of a character.
+    ///
+    /// Defaults to [StopBits::One].
+    pub fn stop_bits(self, stop_bits: StopBits) -> Self {
+        Self { stop_bits, ..self }
+    }
+
+    /// Set the type of signalling to use for controlling data transfer.
+    ///
+    /// Both [FlowControl::Hardware] (RTS/CTS) and [FlowControl::Software]
+    /// (XON/XOFF) are supported. Defaults to [FlowControl::None].
+    pub fn flow_control(self, flow_control: FlowControl) -> Self {
+        Self {
+            flow_control,
+            ..self
+        }
+    }
+
+    /// Set whether the port is opened with exclusive access.
+    ///
+    /// With exclusive access, which is the default, other processes cannot open
+    /// the port while it is open. Disable this to allow, for example, a sniffer
+    /// to read the same port. Data may then be split arbitrarily between the
+    /// processes reading it.
+    pub fn exclusive(self, exclusive: bool) -> Self {
+        Self { exclusive, ..self }
+    }
+
+    /// Set the DTR line to `level` when the port is opened.
+    ///
+    /// Many boards, like most Arduinos, reset when DTR is asserted. Pass
+    /// `false` to open them without a reset. The line is set while the port
+    /// is opened, before the worker threads start. On Linux, the kernel
+    /// asserts DTR for a moment when opening the port regardless, which some
+    /// boards still take as a reset. Ports without control lines, such as
+    /// pseudo-terminals, ignore this. By default, DTR is left as the driver
+    /// sets it.
+    pub fn dtr_on_open(self, level: bool) -> Self {
+        Self {
+            dtr_on_open: Some(level),
+            ..self
+        }
+    }
+
+    /// Set the RTS line to `level` when the port is opened.
+    ///
+    /// The line is set right after the port is opened, before the worker
+    /// threads start. With [SerialPortBuilder::rs485], RTS is then released
+    /// as configured there. Ports without control lines, such as
+    /// pseudo-terminals, ignore this. By default, RTS is left as the driver
+    /// sets it.
+    pub fn rts_on_open(self, level: bool) -> Self {
+        Self {
+            rts_on_open: Some(level),
+            ..self
+        }
+    }
+
+    /// Discard the given buffers of the port when it is opened.
+    ///
+    /// Some USB adapters still hold bytes received before the port was opened,
+    /// e.g. from a previous session. Clearing [ClearBuffer::Input] makes the
+    /// first read return only bytes received afterwards. This happens right
+    /// after the control lines were set, before the worker threads start. By
+    /// default, nothing is cleared.
+    pub fn clear_on_open(self, buffer_to_clear: ClearBuffer) -> Self {
+        Self {
+            clear_on_open: Some(buffer_to_clear),
+            ..self
+        }
+    }
+
+    /// Set whether raw mode is applied again once the port is open.
+    ///
+    /// `serialport` already configures the terminal in raw mode with
+    /// `cfmakeraw(3)` when it opens the port, so no bytes are translated,
+    /// e.g. `\n` to `\r\n` by `ONLCR`, or `\r` to `\n` by `ICRNL`. With
+    /// this enabled, the input, output and local flags set by `cfmakeraw`
+    /// are applied once more after everything else `serialport` configured,
+    /// and a failure to do so fails opening. The line settings such as the
+    /// baud rate, parity and flow control are kept. This is for drivers which
+    /// are known to bring back post-processing. Defaults to `false`.
+    pub fn force_raw_mode(self, force_raw_mode: bool) -> Self {
+        Self {
+            force_raw_mode,
+            ..self
+        }
+    }
+
+    /// Set the amount of time a read waits for data before timing out.
+    ///
+    /// When no data arrives on the port within this duration, reading from
+    /// the [SerialStream] fails with [std::io::ErrorKind::TimedOut], after the
+    /// bytes received before were read. This stops the reader thread, so every
+    /// later read fails in the same way until the port is reopened with
+    /// [SerialStream::reopen]. Writes are not affected. By default, reads wait
+    /// forever.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long a read waits for data before reporting that the line is
+    /// idle.
+    ///
+    /// When no data arrives on the port within this duration, reading from the
+    /// [SerialStream] fails with [std::io::ErrorKind::TimedOut]. Unlike with
+    /// [SerialPortBuilder::timeout], the stream stays usable and the next read
+    /// waits for data again. While the line stays silent, this is reported once
+    /// per `read_idle_timeout`. By default, reads wait forever.
+    pub fn read_idle_timeout(mut self, read_idle_timeout: std::time::Duration) -> Self {
+        self.options.read_idle_timeout = Some(read_idle_timeout);
+        self
+    }
+
+    /// Set how long writing to the port may stall before giving up.
+    ///
+    /// This bounds how long the port may refuse to accept more bytes, for
+    /// example when hardware flow control is enabled and the peer never asserts
+    /// CTS, and how long a flush waits for the hardware to transmit. When
+    /// exceeded, writing to and flushing the [SerialStream] fail with
+    /// [std::io::ErrorKind::TimedOut]. Like other write errors, this ends the
+    /// writer thread, so further writes fail as well. By default, writes wait
+    /// forever.
+    pub fn write_timeout(mut self, write_timeout: std::time::Duration) -> Self {
+        self.options.write_timeout = Some(write_timeout);
+        self
+    }
+
+    /// Set whether the port is opened with `O_NONBLOCK` and the reader thread
+    /// waits for data with `poll(2)`.
+    ///
+    /// By default, the reader thread blocks in reads from the port with a
+    /// timeout of 100 milliseconds, after which it checks whether it should
+    /// stop. Closing or reopening the stream therefore waits up to that long.
+    /// With this enabled, the reader thread instead waits until either the
+    /// port has data or the stream is closed, so that it exits right away, and
+    /// reads from the port never block. Cancellation with
+    /// [SerialPortBuilder::with_cancellation] is still noticed within 100
+    /// milliseconds. Since reads return right away,
+    /// [SerialStream::set_inter_byte_timeout] has no effect. This applies only
+    /// to streams opened with [SerialPortBuilderExt::open_native_async].
+    /// Defaults to `false`.
+    pub fn nonblocking_io(mut self, nonblocking_io: bool) -> Self {
+        self.options.nonblocking_io = nonblocking_io;
+        self
+    }
+
+    /// Set what happens when reading from the port returns no bytes.
+    ///
+    /// Defaults to [EmptyReadPolicy::Retry].
+    pub fn empty_read_policy(mut self, empty_read_policy: EmptyReadPolicy) -> Self {
+        self.options.empty_read_policy = empty_read_policy;
+        self
+    }
+
+    /// Set what happens when the internal buffer for writing is full.
+    ///
+    /// Defaults to [WritePolicy::Block].
+    pub fn write_policy(mut self, write_policy: WritePolicy) -> Self {
+        self.options.write_policy = write_policy;
+        self
+    }
+
+    /// Limit the number of written bytes which have not been handed to the
+    /// port yet.
+    ///
+    /// Besides the internal buffer, the writer thread holds up to a few
+    /// kilobytes it is about to hand to the port. This limits the total of
+    /// both, so that writing applies backpressure, or drops bytes following
+    /// [WritePolicy::DropOldest], once `max_inflight_write` bytes are pending.
+    /// Zero is not a valid limit. By default, there is no limit beyond the size
+    /// of the internal buffer.
+    pub fn max_inflight_write(mut self, max_inflight_write: usize) -> Self {
+        self.options.max_inflight_write = Some(max_inflight_write);
+        self
+    }
+
+    /// Set whether the internal buffer for writing grows without limit.
+    ///
+    /// Writing then never waits for the port, and no bytes are dropped, which
+    /// suits fire-and-forget telemetry where the producer must not be blocked.
+    /// The buffer size set with [SerialPortBuilder::write_buf_size] is not
+    /// used, and the [WritePolicy] has no effect. If the program keeps writing
+    /// faster than the port transmits, memory use grows until it runs out, so
+    /// make sure that the average rate of writing is well below what the port
+    /// can transmit. [SerialPortBuilder::max_inflight_write] still limits the
+    /// bytes pending. Defaults to `false`.
+    pub fn unbounded_write_buffer(mut self, unbounded_write_buffer: bool) -> Self {
+        self.options.unbounded_write_buffer = unbounded_write_buffer;
+        self
+    }
+
+    /// Set the maximum buffer size in the internal buffers.
+    ///
+    /// Each direction has its own internal buffer, and this sets the size of
+    /// both: received bytes are buffered until they are read from the stream,
+    /// and written bytes until the writer thread hands them to the port. Use
+    /// [SerialPortBuilder::read_buf_size] and
+    /// [SerialPortBuilder::write_buf_size] to size them independently.
+    ///
+    /// The size must be at least one, otherwise
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::InvalidInput]. Defaults to 1024.
+    pub fn max_buf_size(mut self, max_buf_size: usize) -> Self {
+        self.options.max_buf_size = max_buf_size;
+        self
+    }
+
+    /// Get the maximum buffer size in the internal buffers.
+    pub fn get_max_buf_size(&self) -> usize {
+        self.options.max_buf_size
+    }
+
+    /// Set the size of the buffer for received bytes, overriding
+    /// [SerialPortBuilder::max_buf_size].
+    ///
+    /// A larger buffer holds more data while the program is busy, before the
+    /// reader thread stops reading from the port.
+    pub fn read_buf_size(mut self, read_buf_size: usize) -> Self {
+        self.options.read_buf_size = Some(read_buf_size);
+        self
+    }
+
+    /// Set the size of the buffer for written bytes, overriding
+    /// [SerialPortBuilder::max_buf_size].
+    ///
+    /// A smaller buffer means less data queued behind a new write, and earlier
+    /// backpressure on a slow port.
+    pub fn write_buf_size(mut self, write_buf_size: usize) -> Self {
+        self.options.write_buf_size = Some(write_buf_size);
+        self
+    }
+
+    /// Set the size of the buffer the reader thread reads from the port into.
+    ///
+    /// This bounds how many bytes a single read from the port returns. Larger
+    /// values reduce the number of system calls on fast links. A value of zero
+    /// is treated as one. Defaults to 1024.
+    pub fn read_chunk_size(mut self, read_chunk_size: usize) -> Self {
+        self.options.read_chunk_size = read_chunk_size;
+        self
+    }
+
+    /// Set the most bytes the writer thread hands to the port at once.
+    ///
+    /// Larger writes are split into several. Some USB adapters delay data in
+    /// the other direction while a large write is being transferred, which
+    /// this avoids on chatty protocols. See also
+    /// [SerialPortBuilder::write_chunk_pause]. A value of zero is treated as
+    /// one. Defaults to [usize::MAX], so that writes are not split.
+    pub fn write_chunk_size(mut self, write_chunk_size: usize) -> Self {
+        self.options.write_chunk_size = write_chunk_size;
+        self
+    }
+
+    /// Set how long the writer thread waits between the parts of a write split
+    /// by [SerialPortBuilder::write_chunk_size].
+    ///
+    /// Defaults to zero, so that the parts are written back to back.
+    pub fn write_chunk_pause(mut self, write_chunk_pause: std::time::Duration) -> Self {
+        self.options.write_chunk_pause = write_chunk_pause;
+        self
+    }
+
+    /// Set whether written bytes are read back instead of being transmitted.
+    ///
+    /// In loopback mode, the worker threads do not transmit or receive on the
+    /// line at all: bytes written to the stream are returned by reads from the
+    /// same stream, and bytes arriving at the port are left unread. The port
+    /// is still opened and configured, and the control lines work as usual.
+    /// This helps to tell whether a problem is in the program or in the
+    /// wiring. Flushing completes once the bytes can be read back, and breaks
+    /// are not sent. Defaults to `false`.
+    pub fn loopback(mut self, loopback: bool) -> Self {
+        self.options.loopback = loopback;
+        self
+    }
+
+    /// Set whether the stream reads, writes or both.
+    ///
+    /// Only the worker threads for the chosen directions are started, see
+    /// [crate::StreamDirection]. [SerialStream::worker_status] reports the
+    /// other thread as not running. Control operations work either way.
+    /// Defaults to [crate::StreamDirection::ReadWrite].
+    pub fn direction(mut self, direction: crate::StreamDirection) -> Self {
+        self.options.direction = direction;
+        self
+    }
+
+    /// Switch the direction of an RS-485 transceiver with RTS.
+    ///
+    /// The writer thread asserts RTS before transmitting and releases it once
+    /// the port reports that the last byte was sent, as described for
+    /// [crate::Rs485Config]. RTS is released when the port is opened. Setting
+    /// RTS with [SerialStream::write_request_to_send] interferes with this.
+    ///
+    /// How soon after the last byte RTS is released depends on the driver
+    /// reporting the end of the transmission, which for some USB adapters
+    /// takes a millisecond or more. Adapters which switch the direction in
+    /// hardware do not need this.
+    pub fn rs485(mut self, config: crate::Rs485Config) -> Self {
+        self.options.rs485 = Some(config);
+        self
+    }
+
+    /// Launch the worker threads with `spawn` instead of [std::thread::spawn].
+    ///
+    /// This is for hosts where threads must be created in a particular way,
+    /// e.g. through a thread pool. `spawn` is called twice per stream, once for
+    /// the reader and once for the writer, and must run the given function on
+    /// its own thread where blocking is allowed. The function returns only
+    /// when the stream is closed, so the thread is occupied until then.
+    /// Dropping the function without running it makes the stream fail as if
+    /// the thread had panicked. Threads launched this way are not named after
+    /// the port.
+    pub fn with_thread_spawner<F>(mut self, spawn: F) -> Self
+    where
+        F: Fn(Box<dyn FnOnce() + Send>) -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        self.options.spawner = Some(ThreadSpawner(Arc::new(spawn)));
+        self
+    }
+
+    /// Pass every chunk of bytes read from or written to the port to `trace`.
+    ///
+    /// This is for debugging protocols, e.g. to log the traffic as hex dumps.
+    /// The worker threads call `trace` right after each read from the port,
+    /// with the bytes exactly as received, and after each write to the port,
+    /// with the bytes handed to it. With [SerialPortBuilder::report_input_errors],
+    /// received bytes include the marks the port adds. No lock is held
+    /// meanwhile, but the thread waits for `trace` to return before it
+    /// continues, so keep it short. Without a tracer, nothing is done.
+    pub fn with_tracer<F>(mut self, trace: F) -> Self
+    where
+        F: Fn(Direction, &[u8]) + Send + Sync + 'static,
+    {
+        self.options.tracer = Some(Tracer(Arc::new(trace)));
+        self
+    }
+
+    /// Stop the stream when `token` is cancelled.
+    ///
+    /// Cancelling the token stops both worker threads, so that the stream
+    /// integrates with the lifecycle of other tasks. Reads and writes then
+    /// fail with [std::io::ErrorKind::ConnectionAborted]. The reader thread
+    /// notices cancellation within about 100 milliseconds. The writer thread
+    /// notices it between writes to the port, so a write stalled by flow
+    /// control delays it unless a [SerialPortBuilder::write_timeout] is set.
+    ///
+    /// The token is kept by clones made with [SerialStream::try_clone] and
+    /// when reopening with [SerialStream::reopen], so a stream reopened after
+    /// cancellation fails right away.
+    pub fn with_cancellation(mut self, token: tokio_util::sync::CancellationToken) -> Self {
+        self.options.cancel = Some(token);
+        self
+    }
+
+    /// Collect received bytes before handing them to the stream, to reduce
+    /// wakeups at the cost of latency.
+    ///
+    /// The reader thread holds on to received bytes until `size` bytes have
+    /// accumulated or `delay` has passed since the first of them arrived,
+    /// whichever comes first. This suits low baud rates, where bytes otherwise
+    /// trickle in one read at a time. `size` must be at least one and at most
+    /// the size of the read buffer, see [SerialPortBuilder::read_buf_size],
+    /// otherwise [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::InvalidInput]. By default, bytes are handed on as
+    /// soon as they are received.
+    pub fn read_coalesce(mut self, delay: std::time::Duration, size: usize) -> Self {
+        self.options.read_coalesce = Some((delay, size));
+        self
+    }
+
+    /// Set whether bytes received with a parity or framing error are
+    /// reported.
+    ///
+    /// By default, the UART drops such bytes or passes them on as they are, so
+    /// corruption goes unnoticed. With this enabled, the port is configured to
+    /// mark them (the `INPCK` and `PARMRK` termios flags, clearing `IGNPAR`),
+    /// and the reader thread fails with [SerialError::CorruptInput] when it
+    /// receives one. Reading returns the bytes received before it first, and
+    /// then fails with [std::io::ErrorKind::InvalidData]. The corrupt byte and
+    /// whatever followed it in the same read are discarded. A break condition
+    /// on the line is reported the same way. Like other read errors,
+    /// this ends the reader thread; [SerialStream::reopen] the port to carry
+    /// on.
+    ///
+    /// Parity errors are detected only with a parity other than
+    /// [Parity::None]. Setting the parity to [Parity::None] with
+    /// [SerialStream::set_parity] after opening stops the detection of
+    /// framing errors. Defaults to `false`.
+    pub fn report_input_errors(mut self, report_input_errors: bool) -> Self {
+        self.options.report_input_errors = report_input_errors;
+        self
+    }
+
+    /// Set whether reading reports a disconnect as the end of the stream.
+    ///
+    /// By default, reading fails with [SerialError::Disconnected] once the
+    /// bytes received before the device went away were read. With this
+    /// enabled, reading returns `Ok(0)` instead, so that e.g.
+    /// [tokio::io::AsyncReadExt::read_to_end] completes with the data received.
+    /// Writing and control operations still fail. [ReconnectingSerial]
+    /// reconnects on errors only, so it does not notice such a disconnect
+    /// while reading. Defaults to `false`.
+    ///
+    /// [ReconnectingSerial]: crate::ReconnectingSerial
+    pub fn disconnect_as_eof(mut self, disconnect_as_eof: bool) -> Self {
+        self.options.disconnect_as_eof = disconnect_as_eof;
+        self
+    }
+
+    /// Check the settings without opening the port.
+    ///
+    /// This fails with [std::io::ErrorKind::InvalidInput] for settings which
+    /// [SerialPortBuilderExt::open_native_async] would reject regardless of
+    /// the device, such as a buffer size of zero, and if the path does not
+    /// name a character device. If there is nothing at the path, this fails
+    /// with [std::io::ErrorKind::NotFound].
+    ///
+    /// Whether the device supports the baud rate, data bits, parity, stop bits
+    /// and flow control is known only once it is opened, as drivers differ.
+    /// On Linux and Android, any baud rate is passed to the driver, which may
+    /// substitute one it supports; opening then fails as described at [new].
+    /// To find out for sure, open the port and drop the stream. This makes a
+    /// blocking system call to look up the path.
+    pub fn validate(&self) -> std::io::Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+
+        self.options.validate()?;
+        let metadata = std::fs::metadata(&self.path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("cannot access {}: {e}", self.path))
+        })?;
+        if !metadata.file_type().is_char_device() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a serial device", self.path),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Open the port like [SerialPortBuilderExt::open_native_async], but
+    /// without blocking the calling task.
+    ///
+    /// Opening a port involves blocking system calls, which can take a while
+    /// for some devices. This runs them on tokio's blocking thread pool, so it
+    /// must be called from within a tokio runtime.
+    pub async fn open_native_async_nonblocking(self) -> std::io::Result<SerialStream> {
+        tokio::task::spawn_blocking(move || self.open_native_async())
+            .await
+            .map_err(std::io::Error::other)?
+    }
+
+    /// Open the port at `baud_rate` with the other settings of the builder.
+    fn open_port(&self, baud_rate: u32) -> std::io::Result<serialport::TTYPort> {
+        let port = serialport::new(&self.path, baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control)
+            .exclusive(self.exclusive);
+        Ok(match self.dtr_on_open {
+            Some(level) => port.dtr_on_open(level),
+            None => port.preserve_dtr_on_open(),
+        }
+        .open_native()?)
+    }
+}
+
+/// Provides a convenience function for maximum compatibility with `tokio-serial`.
+pub trait SerialPortBuilderExt {
+    /// Open a serial port and return it as a [SerialStream].
+    fn open_native_async(self) -> std::io::Result<SerialStream>;
+}
+
+impl SerialPortBuilderExt for SerialPortBuilder {
+    fn open_native_async(self) -> std::io::Result<SerialStream> {
+        let mut port = match self.open_port(self.baud_rate) {
+            // Where `serialport` knows only the standard rates, the port is
+            // opened at one of those and switched to the requested rate below.
+            #[cfg(target_os = "linux")]
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                self.open_port(9600).map_err(|_| e)?
+            }
+            res => res?,
+        };
+        // Like `serialport` does for DTR, this is best effort, as ports without
+        // control lines, such as pseudo-terminals, do not support it.
+        if let Some(level) = self.rts_on_open {
+            let _ = port.write_request_to_send(level);
+        }
+        if let Some(buffer_to_clear) = self.clear_on_open {
+            port.clear(buffer_to_clear)?;
+        }
+        #[cfg(target_os = "linux")]
+        if check_baud_rate(&port, self.baud_rate).is_err() {
+            set_custom_baud_rate(port.as_raw_fd(), self.baud_rate)?;
+        }
+        check_baud_rate(&port, self.baud_rate)?;
+        let raw_fd = port.as_raw_fd();
+        if self.force_raw_mode {
+            make_raw(raw_fd)?;
+        }
+        if self.options.report_input_errors {
+            mark_input_errors(raw_fd)?;
+        }
+        let mut stream = open(Box::new(port), self.options.clone(), Some(raw_fd))?;
+        // `open` keeps this handle for control operations, so the descriptor
+        // stays valid as long as the stream.
+        stream.raw_fd = Some(raw_fd);
+        stream.builder = Some(self);
+        Ok(stream)
+    }
+}
+
+pin_project! {
+    /// An asynchronous implementation of a serial port.
+    ///
+    /// Implements both [tokio::io::AsyncRead] and [tokio::io::AsyncWrite].
+    ///
+    /// Flushing (e.g. with [tokio::io::AsyncWriteExt::flush]) completes only
+    /// once all bytes written before the flush have been transmitted by the
+    /// hardware, as with [std::io::Write::flush] on a [serialport::SerialPort].
+    /// Previous versions of this crate completed a flush as soon as the bytes
+    /// had been handed to the internal buffer.
+    ///
+    /// This could be wrapped with
+    /// [`tokio_util::codec::Framed`](https://docs.rs/tokio-util/0.7.11/tokio_util/codec/struct.Framed.html),
+    /// for example.
+    ///
+    /// Dropping the stream stops both worker threads and closes the port. The
+    /// writer thread exits once it has handed any queued bytes to the port, and
+    /// the reader thread exits within about 100 milliseconds. Each thread holds
+    /// its handle of the port until then, so the port stays open, and locked
+    /// with [SerialPortBuilder::exclusive], for that long after the drop.
+    /// Opening it again right away may fail. [SerialStream::close] and
+    /// [SerialStream::shutdown] wait for the threads to exit instead.
+    ///
+    /// The worker threads and control operations each use their own clone of
+    /// the port handle. If the driver cannot clone its handle, they take turns
+    /// on the one handle instead, with reduced concurrency: reads wait for data
+    /// in slices of 10 milliseconds, in between which writes and control
+    /// operations run, and a write stalled by flow control holds up everything
+    /// else, including the task calling a control method.
+    ///
+    /// If the device goes away, for example when a USB adapter is unplugged,
+    /// reads and writes fail with [std::io::ErrorKind::BrokenPipe]. Other
+    /// failures are reported with different error kinds.
+    ///
+    /// Each direction of the stream is in one of three states:
+    ///
+    /// - *Running*: its worker thread transfers data.
+    /// - *Failed*: its worker thread stopped with an error, and every later
+    ///   read or write, respectively, returns that same error. A failed
+    ///   direction stays failed; the other one keeps working until it fails
+    ///   itself.
+    /// - *Closed*: the stream was shut down, or a [SerialStream::reopen]
+    ///   failed. Reads report the end of the stream, writes fail.
+    ///
+    /// [SerialStream::reopen] moves both directions from any state back to
+    /// *Running* on a newly opened port, discarding the old errors, or to
+    /// *Closed* if opening fails. It can be called again from there. This is
+    /// what [crate::ReconnectingSerial] does automatically. Streams which
+    /// cannot be reopened, and the halves of a split stream, cannot recover
+    /// from a failure.
+    pub struct SerialStream {
+        #[pin]
+        reader: SerialReadHalf,
+        #[pin]
+        writer: SerialWriteHalf,
+        control: std::sync::Mutex<Option<Box<dyn SerialPort>>>,
+        name: Option<String>,
+        raw_fd: Option<std::os::unix::io::RawFd>,
+        // Keeps `raw_fd` open for clones, whose control handle does not expose
+        // its descriptor.
+        fd: Option<std::os::fd::OwnedFd>,
+        // Looked up from the path of `builder` on first use.
+        usb_info: std::sync::OnceLock<Option<UsbPortInfo>>,
+        options: StreamOptions,
+        builder: Option<SerialPortBuilder>,
+    }
+}
+
+pin_project! {
+    /// The reading half of a [SerialStream], created by
+    /// [SerialStream::into_split].
+    ///
+    /// Implements [tokio::io::AsyncRead]. Dropping it stops the reader thread.
+    pub struct SerialReadHalf {
+        #[pin]
+        read_err: WorkerResult,
+        #[pin]
+        reader_channel: ReaderChannel,
+        idle: Arc<IdleSignal>,
+        timeout: Arc<std::sync::Mutex<Option<std::time::Duration>>>,
+        stop_reader: SetOnDrop,
+        // Dropped to wake the reader thread with `nonblocking_io`, after
+        // `stop_reader`.
+        wake_reader: Option<std::os::unix::net::UnixStream>,
+        thread: Option<WorkerThread>,
+        // Set by `SerialStream::clear` to discard the internal buffer before
+        // the next read.
+        cleared: AtomicBool,
+        // Bytes returned by `peek` which were not read yet.
+        peeked: Vec<u8>,
+        // With `disconnect_as_eof`, a disconnect ends the stream like `Eof`.
+        disconnect_as_eof: bool,
+    }
+}
+
+pin_project! {
+    /// The writing half of a [SerialStream], created by
+    /// [SerialStream::into_split].
+    ///
+    /// Implements [tokio::io::AsyncWrite]. Dropping it stops the writer thread
+    /// once it has handed any queued bytes to the port. So does
+    /// [tokio::io::AsyncWriteExt::shutdown], which waits for the thread to exit
+    /// and leaves reading unaffected. Writing fails afterwards.
+    pub struct SerialWriteHalf {
+        #[pin]
+        write_err: WorkerResult,
+        #[pin]
+        writer_sink: WriteSink,
+        queue: Arc<WriteQueue>,
+        write_policy: WritePolicy,
+        // Permits for bytes in flight, with `max_inflight_write`.
+        inflight: Option<tokio_util::sync::PollSemaphore>,
+        commands: tokio::sync::mpsc::UnboundedSender<ControlCommand>,
+        flush_done: Option<tokio::sync::oneshot::Receiver<std::io::Result<()>>>,
+        thread: Option<WorkerThread>,
+        name: Option<String>,
+    }
+}
+
+impl SerialStream {
+    /// Wrap an already opened port in a [SerialStream].
+    ///
+    /// This is an escape hatch for port configuration which
+    /// [SerialPortBuilder] does not cover: configure and open the port with
+    /// [serialport::new], then pass it here. As with
+    /// [SerialPortBuilderExt::open_native_async], reading and writing are
+    /// handled by two newly spawned threads. The timeout of `port` is
+    /// overwritten.
+    pub fn from_serialport(
+        port: Box<dyn SerialPort>,
+        max_buf_size: usize,
+    ) -> std::io::Result<SerialStream> {
+        open(
+            port,
+            StreamOptions {
+                max_buf_size,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Create two streams connected back to back, without a serial port.
+    ///
+    /// Bytes written to one stream can be read from the other, so that code
+    /// using a [SerialStream] can be tested without hardware or virtual
+    /// devices. The streams run worker threads like any other, on top of an
+    /// in-memory port. The control lines are wired as by a null modem cable:
+    /// setting RTS on one stream sets CTS on the other, and setting DTR sets
+    /// DSR and CD. Dropping one stream makes reads and writes on the other fail
+    /// with [std::io::ErrorKind::BrokenPipe], as with an unplugged device.
+    ///
+    /// The streams have no name and cannot be reopened. This requires the
+    /// `test-util` feature.
+    #[cfg(feature = "test-util")]
+    pub fn pair() -> std::io::Result<(SerialStream, SerialStream)> {
+        let (a, b) = crate::memory::MemoryPort::pair();
+        Ok((
+            open(Box::new(a), StreamOptions::default(), None)?,
+            open(Box::new(b), StreamOptions::default(), None)?,
+        ))
+    }
+
+    /// Close the port and open it again with the original settings.
+    ///
+    /// This is meant for devices which disappear and come back under the same
+    /// path, like a USB adapter dropping off the bus. Bytes waiting in the
+    /// internal buffers of the old connection are discarded.
+    ///
+    /// This blocks the calling thread until the old worker threads have
+    /// exited, usually for at most about 100 milliseconds, and while the port
+    /// is opened again. To keep other tasks running meanwhile, move the stream
+    /// into [tokio::task::spawn_blocking] to reopen it. If opening the port
+    /// again fails, the stream stays closed and `reopen` may be retried.
+    /// Reading from a closed stream reports the end of the stream, writing to
+    /// it fails.
+    ///
+    /// Only streams opened with [SerialPortBuilderExt::open_native_async] can
+    /// be reopened. For others, this fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn reopen(&mut self) -> std::io::Result<()> {
+        let mut builder = self.builder.clone().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "only streams opened from a SerialPortBuilder can be reopened",
+            )
+        })?;
+        self.writer.stop_and_join();
+        self.reader.stop_and_join();
+        // The control handle shares the exclusive lock on the port.
+        self.control
+            .get_mut()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take();
+        self.fd.take();
+        builder.options.counters = self.options.counters.clone();
+        *self = builder.open_native_async()?;
+        Ok(())
+    }
+
+    /// Create a second stream on the same port.
+    ///
+    /// The new stream uses a clone of the port handle with its own worker
+    /// threads and the same settings as this one. Both streams can be used at
+    /// the same time, for example from different tasks.
+    ///
+    /// Reads are not duplicated: each byte arriving on the port is returned by
+    /// only one of the streams. Bytes written to one stream are transmitted in
+    /// order, but when both streams write concurrently their bytes may be
+    /// interleaved in chunks of up to a few kilobytes. To send messages which
+    /// must not be interleaved, write them from a single stream. Flushing
+    /// either stream waits until everything written to the port so far has
+    /// been transmitted.
+    ///
+    /// The port is closed once all clones are dropped. A clone can be reopened
+    /// with [SerialStream::reopen] only after all other clones were dropped.
+    /// A clone has a file descriptor of its own, see [SerialStream::raw_fd],
+    /// if this stream has one.
+    pub fn try_clone(&self) -> std::io::Result<SerialStream> {
+        let port = self.with_control(|port| port.try_clone())?;
+        let options = StreamOptions {
+            timeout: *lock(&self.reader.timeout),
+            ..self.options.clone()
+        };
+        // A duplicate refers to the same open port as the cloned handle.
+        let fd = match self.raw_fd {
+            // SAFETY: `with_fd` holds the control handle, which keeps the
+            // descriptor open.
+            Some(_) => Some(self.with_fd(|raw_fd| {
+                unsafe { std::os::fd::BorrowedFd::borrow_raw(raw_fd) }.try_clone_to_owned()
+            })?),
+            None => None,
+        };
+        let mut stream = open(port, options, self.raw_fd)?;
+        stream.raw_fd = fd.as_ref().map(std::os::fd::AsRawFd::as_raw_fd);
+        stream.fd = fd;
+        stream.usb_info = self.usb_info.clone();
+        stream.builder = self.builder.clone();
+        Ok(stream)
+    }
+
+    /// Transmit all written bytes, then close the port.
+    ///
+    /// This waits until the hardware has sent everything written so far, as
+    /// with [tokio::io::AsyncWriteExt::flush], and then stops both worker
+    /// threads and waits for them to exit. Unlike
+    /// [tokio::io::AsyncWriteExt::shutdown], which only stops the writer
+    /// thread, the port is closed once this returns.
+    ///
+    /// Waiting for the worker threads happens on tokio's blocking thread pool,
+    /// so this must be called from within a tokio runtime. If transmitting
+    /// fails, the port is closed anyway and the error is returned.
+    pub async fn shutdown(self) -> std::io::Result<()> {
+        self.stop().await?.1
+    }
+
+    /// Transmit all written bytes, close the port and report whether the
+    /// stream failed.
+    ///
+    /// This works like [SerialStream::shutdown], and additionally fails if a
+    /// worker thread failed before, e.g. because the device went away, even if
+    /// the error was not returned by a read or write yet. Read timeouts are not
+    /// reported. If flushing failed as well, the error of the thread is
+    /// returned, as it is usually the cause. This is the counterpart of
+    /// checking the result of closing a file, for callers who must know that
+    /// the stream ended cleanly. Dropping the stream instead closes it without
+    /// reporting anything.
+    pub async fn close(self) -> std::io::Result<()> {
+        let (mut stream, flushed) = self.stop().await?;
+        // The threads have exited, so their outcomes are known.
+        for res in [
+            (&mut stream.reader.read_err).now_or_never(),
+            (&mut stream.writer.write_err).now_or_never(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            match res {
+                Err(e) if !is_clean_exit(&e) => return to_std_io(Err(e)),
+                _ => {}
+            }
+        }
+        flushed
+    }
+
+    /// Flush the stream if it writes, then stop both worker threads and wait
+    /// for them to exit, returning the stream along with the outcome of
+    /// flushing.
+    async fn stop(mut self) -> std::io::Result<(Self, std::io::Result<()>)> {
+        let flushed = match self.options.direction.writes() {
+            true => tokio::io::AsyncWriteExt::flush(&mut self.writer).await,
+            false => Ok(()),
+        };
+        let stream = tokio::task::spawn_blocking(move || {
+            self.writer.stop_and_join();
+            self.reader.stop_and_join();
+            self
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+        Ok((stream, flushed))
+    }
+
+    /// Split the stream into a reading half and a writing half.
+    ///
+    /// The halves can be moved to different tasks and used concurrently. Each
+    /// half reports the errors of its own worker thread. The control methods of
+    /// [SerialStream], such as [SerialStream::set_baud_rate], are not available
+    /// on the halves.
+    ///
+    /// This mirrors `tokio::net::TcpStream::into_split`.
+    pub fn into_split(self) -> (SerialReadHalf, SerialWriteHalf) {
+        (self.reader, self.writer)
+    }
+
+    /// Write all of `data` and wait until the port has transmitted it.
+    ///
+    /// This resolves once the writer thread has handed the bytes to the port
+    /// and the port reports that they left its transmit buffer, which is what
+    /// [tokio::io::AsyncWriteExt::flush] waits for after the write. Bytes
+    /// written earlier are transmitted first. Use this for protocols with
+    /// strict timing between frames. How closely the returned future follows
+    /// the last bit on the wire depends on the driver; for USB adapters it may
+    /// lag by a millisecond or more.
+    pub async fn write_and_confirm(&mut self, data: &[u8]) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        self.write_all(data).await?;
+        self.flush().await
+    }
+
+    /// Write all of `data`, failing with [std::io::ErrorKind::TimedOut] if it
+    /// cannot be written within `dur`.
+    ///
+    /// Like [tokio::io::AsyncWriteExt::write_all], this completes once the
+    /// stream accepted all bytes, not once they were transmitted; use
+    /// [SerialStream::write_and_confirm] to wait for that. On timeout, the
+    /// first part of `data` may already have been accepted and will still be
+    /// transmitted, so the peer may receive an incomplete message.
+    pub async fn write_all_timeout(
+        &mut self,
+        data: &[u8],
+        dur: std::time::Duration,
+    ) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        tokio::time::timeout(dur, self.write_all(data))
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "write timed out"))?
+    }
+
+    /// Read like [tokio::io::AsyncReadExt::read], failing with
+    /// [std::io::ErrorKind::TimedOut] if no bytes arrived by `at`.
+    ///
+    /// This is cancellation safe: if it times out or the future is dropped,
+    /// no bytes were taken from the stream, and they are returned by the next
+    /// read.
+    pub async fn read_deadline(
+        &mut self,
+        buf: &mut [u8],
+        at: tokio::time::Instant,
+    ) -> std::io::Result<usize> {
+        use tokio::io::AsyncReadExt;
+        tokio::time::timeout_at(at, self.read(buf))
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "read timed out"))?
+    }
+
+    /// Receive bytes without removing them from the stream.
+    ///
+    /// This waits until at least one byte is available and copies up to
+    /// `buf.len()` bytes, which are returned again by the next peek or read.
+    /// See [SerialReadHalf::peek] for details.
+    pub async fn peek(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.peek(buf).await
+    }
+
+    /// Take the bytes currently held in the internal buffer without waiting
+    /// for more. See [SerialReadHalf::take_buffered] for details.
+    pub fn take_buffered(&mut self) -> Vec<u8> {
+        self.reader.take_buffered()
+    }
+
+    /// Read until `delim` has been received and return everything read,
+    /// including `delim`.
+    ///
+    /// This reads byte by byte, so that nothing after `delim` is consumed. If
+    /// `max` bytes were read without the last of them completing `delim`, this
+    /// fails with [std::io::ErrorKind::InvalidData] and the bytes read are
+    /// lost. If the stream ends first, this fails with
+    /// [std::io::ErrorKind::UnexpectedEof].
+    pub async fn read_until(&mut self, delim: &[u8], max: usize) -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        if delim.is_empty() {
+            return Ok(buf);
+        }
+        let mut byte = [0u8];
+        while buf.len() < max {
+            if self.read(&mut byte).await? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            buf.push(byte[0]);
+            if buf.ends_with(delim) {
+                return Ok(buf);
+            }
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "delimiter not found within the maximum length",
+        ))
+    }
+
+    /// Send `cmd` and read the response up to and including `delim`.
+    ///
+    /// This writes and flushes `cmd`, then reads as with
+    /// [SerialStream::read_until]. Bytes received before `cmd` is written are
+    /// kept and read first, which suits devices that also send unsolicited
+    /// messages. To discard them, use [SerialStream::transact_clearing].
+    ///
+    /// If the whole exchange does not complete within `timeout`, this fails
+    /// with [std::io::ErrorKind::TimedOut]. The part of the response which was
+    /// read by then is lost, and the rest of it, should it arrive later, is
+    /// left in the stream.
+    pub async fn transact(
+        &mut self,
+        cmd: &[u8],
+        delim: &[u8],
+        timeout: std::time::Duration,
+    ) -> std::io::Result<Vec<u8>> {
+        use tokio::io::AsyncWriteExt;
+        let exchange = async {
+            self.write_all(cmd).await?;
+            self.flush().await?;
+            self.read_until(delim, usize::MAX).await
+        };
+        tokio::time::timeout(timeout, exchange).await.map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "no response within the timeout",
+            )
+        })?
+    }
+
+    /// Like [SerialStream::transact], but discard the bytes received before
+    /// `cmd` is written first, as with [SerialStream::clear], so that a stale
+    /// response is not mistaken for the new one.
+    pub async fn transact_clearing(
+        &mut self,
+        cmd: &[u8],
+        delim: &[u8],
+        timeout: std::time::Duration,
+    ) -> std::io::Result<Vec<u8>> {
+        self.clear(ClearBuffer::Input)?;
+        // The response must not be discarded along with the stale bytes.
+        self.reader.discard_if_cleared();
+        self.transact(cmd, delim, timeout).await
+    }
+
+    /// Read the stream as newline-delimited lines.
+    ///
+    /// Lines are split on `\n` and returned without the line ending, which may
+    /// also be `\r\n`. This is a shorthand for wrapping the stream in a
+    /// [tokio_util::codec::FramedRead] with a [tokio_util::codec::LinesCodec].
+    ///
+    /// A line longer than `max_length` bytes is reported as an error of kind
+    /// [std::io::ErrorKind::InvalidData], after which the returned stream ends.
+    /// Use [usize::MAX] to allow lines of any length. When reading fails, for
+    /// example because the device was unplugged, the incomplete last line, if
+    /// any, is returned first, then the error, and then the returned stream
+    /// ends.
+    pub fn lines(
+        self,
+        max_length: usize,
+    ) -> impl futures::Stream<Item = std::io::Result<String>> + Send {
+        let framed = tokio_util::codec::FramedRead::new(
+            EofOnError {
+                inner: self,
+                error: None,
+                failed: false,
+            },
+            tokio_util::codec::LinesCodec::new_with_max_length(max_length),
+        );
+        futures::stream::unfold(framed, |mut framed| async move {
+            match framed.next().await {
+                Some(line) => Some((line.map_err(lines_error), framed)),
+                None => framed.get_mut().error.take().map(|e| (Err(e), framed)),
+            }
+        })
+    }
+
+    /// Read the stream as chunks of bytes.
+    ///
+    /// Each item holds the bytes available when it was read, up to the
+    /// maximum buffer size. This is a shorthand for wrapping the stream in a
+    /// [tokio_util::io::ReaderStream]. When reading fails, the error is
+    /// returned and then the returned stream ends.
+    pub fn into_byte_stream(
+        self,
+    ) -> impl futures::Stream<Item = std::io::Result<tokio_util::bytes::Bytes>> + Send {
+        let capacity = self.options.read_buf_size();
+        tokio_util::io::ReaderStream::with_capacity(self, capacity)
+    }
+
+    /// Write packets to the stream with an encoder.
+    ///
+    /// This is a shorthand for wrapping the stream in a
+    /// [tokio_util::codec::FramedWrite], which implements [futures::Sink] for
+    /// every item type `codec` can encode. [futures::SinkExt::send] encodes a
+    /// packet and flushes it, so it completes once the packet has been
+    /// transmitted. [futures::SinkExt::feed] only encodes it, which lets
+    /// several packets be flushed together. The stream remains reachable with
+    /// [tokio_util::codec::FramedWrite::get_ref], e.g. to use its control
+    /// methods.
+    pub fn into_packet_sink<C>(self, codec: C) -> tokio_util::codec::FramedWrite<Self, C> {
+        tokio_util::codec::FramedWrite::new(self, codec)
+    }
+
+    /// Read the stream as packets framed with [crate::CobsCodec].
+    ///
+    /// Packets longer than `max_length` bytes and corrupt frames are reported
+    /// as errors of kind [std::io::ErrorKind::InvalidData], after which reading
+    /// continues with the next frame. When reading fails, for example because
+    /// the device was unplugged, the error is returned and then the returned
+    /// stream ends. An incomplete last frame is dropped.
+    #[cfg(feature = "cobs")]
+    pub fn cobs_frames(
+        self,
+        max_length: usize,
+    ) -> impl futures::Stream<Item = std::io::Result<Vec<u8>>> + Send {
+        use tokio_util::codec::Decoder;
+        let codec = crate::CobsCodec::new_with_max_length(max_length);
+        let buf = tokio_util::bytes::BytesMut::new();
+        futures::stream::unfold(Some((self, codec, buf)), |state| async move {
+            let (mut stream, mut codec, mut buf) = state?;
+            loop {
+                match codec.decode(&mut buf) {
+                    Ok(Some(packet)) => return Some((Ok(packet), Some((stream, codec, buf)))),
+                    Ok(None) => {}
+                    Err(e) => return Some((Err(e), Some((stream, codec, buf)))),
+                }
+                match stream.read_buf(&mut buf).await {
+                    Ok(0) => return None,
+                    Ok(_) => {}
+                    Err(e) => return Some((Err(e), None)),
+                }
+            }
+        })
+    }
+
+    /// Get the name of the port, usually its device path.
+    ///
+    /// This mirrors [serialport::SerialPort::name] and is recorded when the
+    /// port is opened.
+    pub fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    /// Get the file descriptor of the port, if known.
+    ///
+    /// This is meant for identifying the device, e.g. with `fstat` to match it
+    /// against udev or sysfs, not for I/O. It is `None` for streams not opened
+    /// with [SerialPortBuilderExt::open_native_async] or cloned from one with
+    /// [SerialStream::try_clone]. See the
+    /// [std::os::unix::io::AsRawFd] implementation for what must not be done
+    /// with the descriptor.
+    pub fn raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        self.raw_fd
+    }
+
+    /// Get the current baud rate of the open port.
+    ///
+    /// This queries the port, so it reports the rate the OS actually has set
+    /// rather than the one that was requested.
+    pub fn baud_rate(&self) -> std::io::Result<u32> {
+        self.with_control(|port| port.baud_rate())
+    }
+
+    /// Change the amount of time a read waits for data before timing out.
+    ///
+    /// This replaces the value set with [SerialPortBuilder::timeout] and takes
+    /// effect within about 100 milliseconds. The time without data is counted
+    /// from the change, so lowering the timeout does not make a read time out
+    /// immediately. Once a read has timed out, the stream is closed and this
+    /// has no effect.
+    pub fn set_read_timeout(&self, timeout: std::time::Duration) -> std::io::Result<()> {
+        *lock(&self.reader.timeout) = Some(timeout);
+        Ok(())
+    }
+
+    /// Get the settings the OS actually has set for the open port.
+    ///
+    /// This queries the port, so a driver ignoring a requested setting shows
+    /// up here.
+    pub fn settings(&self) -> std::io::Result<SerialPortSettings> {
+        self.with_control(|port| {
+            Ok(SerialPortSettings {
+                baud_rate: port.baud_rate()?,
+                data_bits: port.data_bits()?,
+                parity: port.parity()?,
+                stop_bits: port.stop_bits()?,
+                flow_control: port.flow_control()?,
+            })
+        })
+    }
+
+    /// Set the baud rate of the open port.
+    ///
+    /// The new rate takes effect immediately. Bytes already written but still
+    /// waiting in the internal buffer or in the OS transmit buffer are not
+    /// flushed first, so they may go out at the new rate. Use
+    /// [SerialStream::set_baud_rate_queued] to change the rate after them.
+    pub fn set_baud_rate(&self, baud_rate: u32) -> std::io::Result<()> {
+        self.with_control(|port| port.set_baud_rate(baud_rate))
+    }
+
+    /// Set the number of bits used to represent a character sent on the line.
+    ///
+    /// Like [SerialStream::set_baud_rate], this takes effect immediately, and
+    /// bytes still waiting to be transmitted may go out with the new framing.
+    /// To switch framing between messages, flush the stream first, e.g. with
+    /// [tokio::io::AsyncWriteExt::flush], and make sure the peer has switched
+    /// too before writing more.
+    pub fn set_data_bits(&self, data_bits: DataBits) -> std::io::Result<()> {
+        self.with_control(|port| port.set_data_bits(data_bits))
+    }
+
+    /// Set the type of parity to use for error checking.
+    ///
+    /// This takes effect immediately, as described for
+    /// [SerialStream::set_data_bits].
+    pub fn set_parity(&self, parity: Parity) -> std::io::Result<()> {
+        self.with_control(|port| port.set_parity(parity))
+    }
+
+    /// Set the number of bits to use to signal the end of a character.
+    ///
+    /// This takes effect immediately, as described for
+    /// [SerialStream::set_data_bits].
+    pub fn set_stop_bits(&self, stop_bits: StopBits) -> std::io::Result<()> {
+        self.with_control(|port| port.set_stop_bits(stop_bits))
+    }
+
+    /// Set the type of signalling to use for controlling data transfer.
+    ///
+    /// This takes effect immediately, for bytes still waiting to be
+    /// transmitted as well. After switching to [FlowControl::Software], an
+    /// XOFF received from the peer pauses the transmission of those bytes
+    /// until an XON arrives, and XON and XOFF bytes are removed from the data
+    /// received from then on. Flush the stream first, as described for
+    /// [SerialStream::set_data_bits], to switch between messages.
+    pub fn set_flow_control(&self, flow_control: FlowControl) -> std::io::Result<()> {
+        self.with_control(|port| port.set_flow_control(flow_control))
+    }
+
+    /// Set whether other processes are prevented from opening the port.
+    ///
+    /// This claims or releases exclusive access, as
+    /// [SerialPortBuilder::exclusive] does when opening, e.g. to lock a device
+    /// only after confirming its identity. Processes which already have the
+    /// port open are not affected. [SerialStream::reopen] opens the port with
+    /// the setting of the builder again.
+    ///
+    /// This needs the file descriptor of the port, so it fails with
+    /// [std::io::ErrorKind::Unsupported] for streams without one, see
+    /// [SerialStream::raw_fd].
+    pub fn set_exclusive(&self, exclusive: bool) -> std::io::Result<()> {
+        let request = if exclusive {
+            libc::TIOCEXCL
+        } else {
+            libc::TIOCNXCL
+        };
+        self.with_fd(|fd| {
+            // SAFETY: These requests take no argument and only change the
+            // state of the terminal.
+            if unsafe { libc::ioctl(fd, request as _) } == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        })
+    }
+
+    /// Set the `VMIN` and `VTIME` termios settings of the port.
+    ///
+    /// These control when a read from the port returns: once `vmin` bytes
+    /// have arrived, or once no byte has arrived for `vtime_ds` tenths of a
+    /// second after the last one. Ports are opened with a `vmin` of one and a
+    /// `vtime_ds` of zero, so that a read returns as soon as a byte is
+    /// available. Setting them changes the granularity in which the reader
+    /// thread hands on received bytes, e.g. to receive a Modbus RTU frame,
+    /// which ends with a silence, in one piece. Reads from the stream still
+    /// return all bytes available, which may span several such pieces.
+    /// `vtime_ds` has a resolution of 100 milliseconds, so it only
+    /// approximates silences at higher baud rates. With a `vmin` of zero,
+    /// reads from the port may return no bytes, see [EmptyReadPolicy].
+    ///
+    /// This needs the file descriptor of the port, so it fails with
+    /// [std::io::ErrorKind::Unsupported] for streams without one, see
+    /// [SerialStream::raw_fd].
+    pub fn set_inter_byte_timeout(&self, vmin: u8, vtime_ds: u8) -> std::io::Result<()> {
+        self.with_fd(|fd| {
+            update_termios(fd, |termios| {
+                termios.c_cc[libc::VMIN] = vmin;
+                termios.c_cc[libc::VTIME] = vtime_ds;
+            })
+        })
+    }
+
+    /// Set the state of the Data Terminal Ready (DTR) control line.
+    ///
+    /// The line changes as soon as this returns, so it is set before any bytes
+    /// written afterwards. Bytes written earlier may still be waiting in the
+    /// internal buffer and can go out after the change.
+    pub fn write_data_terminal_ready(&self, level: bool) -> std::io::Result<()> {
+        self.with_control(|port| port.write_data_terminal_ready(level))
+    }
+
+    /// Set the state of the Request To Send (RTS) control line.
+    ///
+    /// As with [SerialStream::write_data_terminal_ready], the line changes as
+    /// soon as this returns. Asserting RTS and then writing a frame therefore
+    /// guarantees the line is asserted before the frame goes out. Deasserting
+    /// RTS right after a write, however, may happen while bytes of that write
+    /// are still waiting in the internal buffer; use
+    /// [SerialStream::write_request_to_send_queued] for that.
+    pub fn write_request_to_send(&self, level: bool) -> std::io::Result<()> {
+        self.with_control(|port| port.write_request_to_send(level))
+    }
+
+    /// Read the state of the Clear To Send (CTS) control line.
+    ///
+    /// Like the other control line methods, this does not wait for pending
+    /// reads or writes.
+    pub fn read_clear_to_send(&self) -> std::io::Result<bool> {
+        self.with_control(|port| port.read_clear_to_send())
+    }
+
+    /// Read the state of the Data Set Ready (DSR) control line.
+    pub fn read_data_set_ready(&self) -> std::io::Result<bool> {
+        self.with_control(|port| port.read_data_set_ready())
+    }
+
+    /// Read the state of the Carrier Detect (CD) control line.
+    pub fn read_carrier_detect(&self) -> std::io::Result<bool> {
+        self.with_control(|port| port.read_carrier_detect())
+    }
+
+    /// Read the state of the Ring Indicator (RI) control line.
+    pub fn read_ring_indicator(&self) -> std::io::Result<bool> {
+        self.with_control(|port| port.read_ring_indicator())
+    }
+
+    /// Get the number of bytes waiting in the OS input buffer.
+    ///
+    /// This does not include bytes which the reader thread has already moved
+    /// into the internal buffer. Those are returned by the next read
+    /// immediately, so a read may return more bytes than reported here.
+    pub fn bytes_to_read(&self) -> std::io::Result<u32> {
+        self.with_control(|port| port.bytes_to_read())
+    }
+
+    /// Get the number of bytes waiting in the OS output buffer.
+    ///
+    /// This does not include bytes still in the internal buffer, which the
+    /// writer thread has not handed to the port yet. After a flush (e.g. with
+    /// [tokio::io::AsyncWriteExt::flush]) completes, there are no such bytes.
+    /// Use this to check whether a transmission has finished.
+    pub fn bytes_to_write(&self) -> std::io::Result<u32> {
+        self.with_control(|port| port.bytes_to_write())
+    }
+
+    /// Get the number of bytes in the internal buffer which the writer thread
+    /// has not handed to the port yet.
+    ///
+    /// This is a hint only, as the writer thread keeps taking bytes. Together
+    /// with [SerialStream::bytes_to_write], it tells how much written data has
+    /// not been transmitted.
+    pub fn write_buffer_len(&self) -> usize {
+        self.writer.write_buffer_len()
+    }
+
+    /// Wait until the writer thread has handed all bytes written so far to the
+    /// port, without waiting for the port to transmit them.
+    ///
+    /// See [SerialWriteHalf::flush_software].
+    pub async fn flush_software(&mut self) -> std::io::Result<()> {
+        self.writer.flush_software().await
+    }
+
+    /// Get the USB device information of the port, such as its manufacturer
+    /// and product strings.
+    ///
+    /// This is looked up among the available ports on the first call, which
+    /// involves blocking system calls, and kept until the port is reopened
+    /// with [SerialStream::reopen]. It is `None` if the port is not a USB
+    /// device, its information could not be enumerated, or the stream was not
+    /// opened from a path.
+    pub fn usb_info(&self) -> Option<UsbPortInfo> {
+        self.usb_info
+            .get_or_init(|| {
+                self.builder
+                    .as_ref()
+                    .and_then(|builder| find_usb_info(&builder.path))
+            })
+            .clone()
+    }
+
+    /// Get the counters of bytes received and transmitted.
+    ///
+    /// The counters are shared with clones made by [SerialStream::try_clone]
+    /// and kept by [SerialStream::reopen], so that they cover the port rather
+    /// than a single stream. Hold on to them to read them from another task,
+    /// e.g. to export metrics.
+    pub fn counters(&self) -> Arc<SerialCounters> {
+        self.options.counters.clone().unwrap_or_default()
+    }
+
+    /// Get the number of bytes received from the port so far.
+    ///
+    /// Bytes are counted when the reader thread receives them, which may be
+    /// before they are read from the stream.
+    pub fn bytes_read(&self) -> u64 {
+        self.counters().bytes_read()
+    }
+
+    /// Get the number of bytes handed to the port for transmission so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.counters().bytes_written()
+    }
+
+    /// Get when bytes were last received from the port, if ever.
+    ///
+    /// Like [SerialStream::bytes_read], this covers the port across clones and
+    /// reopens. A watchdog can compare it with the current time to notice a
+    /// stalled link which did not fail.
+    pub fn last_read_at(&self) -> Option<std::time::Instant> {
+        self.counters().last_read_at()
+    }
+
+    /// Get when bytes were last handed to the port for transmission, if ever.
+    pub fn last_write_at(&self) -> Option<std::time::Instant> {
+        self.counters().last_write_at()
+    }
+
+    /// Get whether the reader and writer threads are still running.
+    ///
+    /// A worker thread exits when it fails, e.g. because the device was
+    /// unplugged, or when the stream is shut down. Once a thread has exited,
+    /// the stream must be reopened with [SerialStream::reopen] to resume. This
+    /// does not block and can be polled to supervise a stream.
+    pub fn worker_status(&self) -> WorkerStatus {
+        WorkerStatus {
+            reader_alive: is_alive(&self.reader.thread),
+            writer_alive: is_alive(&self.writer.thread),
+        }
+    }
+
+    /// Wait until the device is confirmed to be live.
+    ///
+    /// Some devices, such as USB CDC adapters, can be opened a moment before
+    /// they can transfer data. This resolves once reading the control lines
+    /// succeeds or bytes are received after this was called, whichever comes
+    /// first, checking every 10 milliseconds. For ports without control
+    /// lines, such as pseudo-terminals, that means waiting for data. This
+    /// fails if a worker thread stops meanwhile, e.g. because the device went
+    /// away, or if the stream is closed. Use [tokio::time::timeout] to give
+    /// up eventually.
+    pub async fn ready(&self) -> std::io::Result<()> {
+        let bytes_read = self.bytes_read();
+        loop {
+            match self.with_control(|port| port.read_clear_to_send()) {
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotConnected => return Err(e),
+                Err(_) => {}
+            }
+            if self.bytes_read() != bytes_read {
+                return Ok(());
+            }
+            let exited = |thread: &Option<WorkerThread>| {
+                thread.as_ref().is_some_and(|thread| thread.is_finished())
+            };
+            if exited(&self.reader.thread) || exited(&self.writer.thread) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "worker thread stopped before the device became ready",
+                ));
+            }
+            tokio::time::sleep(READY_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Transmit a break condition for `duration`.
+    ///
+    /// All bytes written before this call are transmitted before the break
+    /// begins. Bytes written while the break is in progress are held in the
+    /// internal buffer and transmitted after it ends. The break is timed by the
+    /// writer thread, so the runtime is not blocked, and the line is returned
+    /// to normal even if the returned future is dropped early.
+    pub async fn send_break(&self, duration: std::time::Duration) -> std::io::Result<()> {
+        self.writer
+            .command(|done| ControlCommand::Break(duration, done))
+            .await
+    }
+
+    /// Run `f` on the port once all bytes written before this call have been
+    /// transmitted.
+    ///
+    /// Unlike the control methods such as [SerialStream::set_baud_rate],
+    /// which take effect immediately, this is ordered with writes: the writer
+    /// thread transmits everything written so far, as for a flush, then
+    /// holds back bytes written afterwards until `f` returns. Operations
+    /// queued this way, flushes and breaks are handled in the order they were
+    /// called. This fails if transmitting the earlier bytes fails, in which
+    /// case `f` is not run.
+    ///
+    /// `f` runs on the handle used for control operations, on the calling
+    /// task, so it should be quick.
+    pub async fn queue_control<T>(
+        &self,
+        f: impl FnOnce(&mut dyn SerialPort) -> serialport::Result<T>,
+    ) -> std::io::Result<T> {
+        let (resume, paused) = tokio::sync::oneshot::channel();
+        self.writer
+            .command(|done| ControlCommand::Pause(done, paused))
+            .await?;
+        let res = self.with_control(f);
+        drop(resume);
+        res
+    }
+
+    /// Set the baud rate once all bytes written before have been transmitted,
+    /// see [SerialStream::queue_control].
+    pub async fn set_baud_rate_queued(&self, baud_rate: u32) -> std::io::Result<()> {
+        self.queue_control(|port| port.set_baud_rate(baud_rate))
+            .await
+    }
+
+    /// Set the state of the Request To Send (RTS) control line once all bytes
+    /// written before have been transmitted, see
+    /// [SerialStream::queue_control].
+    pub async fn write_request_to_send_queued(&self, level: bool) -> std::io::Result<()> {
+        self.queue_control(|port| port.write_request_to_send(level))
+            .await
+    }
+
+    /// Set the state of the Data Terminal Ready (DTR) control line once all
+    /// bytes written before have been transmitted, see
+    /// [SerialStream::queue_control].
+    pub async fn write_data_terminal_ready_queued(&self, level: bool) -> std::io::Result<()> {
+        self.queue_control(|port| port.write_data_terminal_ready(level))
+            .await
+    }
+
+    /// Discard bytes in the input buffer, the output buffer, or both.
+    ///
+    /// Clearing the input also discards the bytes in the internal buffer when
+    /// the stream is next read, so that read returns only bytes received after
+    /// this call. Bytes arriving while `clear` runs, or before the next read,
+    /// may be discarded as well.
+    ///
+    /// Clearing the output discards bytes in the OS transmit buffer only.
+    /// Bytes still waiting in the internal write buffer are transmitted.
+    pub fn clear(&self, buffer_to_clear: ClearBuffer) -> std::io::Result<()> {
+        self.with_control(|port| port.clear(buffer_to_clear))?;
+        if matches!(buffer_to_clear, ClearBuffer::Input | ClearBuffer::All) {
+            self.reader.cleared.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+/// The settings of an open port, returned by [SerialStream::settings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialPortSettings {
+    /// The baud rate in symbols per second.
+    pub baud_rate: u32,
+    /// The number of bits used to represent a character sent on the line.
+    pub data_bits: DataBits,
+    /// The type of parity used for error checking.
+    pub parity: Parity,
+    /// The number of bits used to signal the end of a character.
+    pub stop_bits: StopBits,
+    /// The type of signalling used for controlling data transfer.
+    pub flow_control: FlowControl,
+}
+
+/// Counters of the bytes passing through a port, returned by
+/// [SerialStream::counters].
+///
+/// The worker threads update these as they go. Reading them is cheap and does
+/// not block.
+#[derive(Debug)]
+pub struct SerialCounters {
+    bytes_read: std::sync::atomic::AtomicU64,
+    bytes_written: std::sync::atomic::AtomicU64,
+    /// When bytes were last received and handed to the port, in nanoseconds
+    /// after `epoch` plus one, or zero if never.
+    last_read: std::sync::atomic::AtomicU64,
+    last_write: std::sync::atomic::AtomicU64,
+    epoch: std::time::Instant,
+}
+
+impl Default for SerialCounters {
+    fn default() -> Self {
+        Self {
+            bytes_read: Default::default(),
+            bytes_written: Default::default(),
+            last_read: Default::default(),
+            last_write: Default::default(),
+            epoch: std::time::Instant::now(),
+        }
+    }
+}
+
+impl SerialCounters {
+    /// Get the number of bytes received from the port so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of bytes handed to the port for transmission so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Get when bytes were last received from the port, if ever.
+    pub fn last_read_at(&self) -> Option<std::time::Instant> {
+        self.instant(&self.last_read)
+    }
+
+    /// Get when bytes were last handed to the port for transmission, if ever.
+    pub fn last_write_at(&self) -> Option<std::time::Instant> {
+        self.instant(&self.last_write)
+    }
+
+    /// Count `sz` bytes received at `at`.
+    fn count_read(&self, sz: usize, at: std::time::Instant) {
+        self.bytes_read.fetch_add(sz as u64, Ordering::Relaxed);
+        self.last_read.store(self.timestamp(at), Ordering::Relaxed);
+    }
+
+    /// Count `sz` bytes handed to the port at `at`.
+    fn count_written(&self, sz: usize, at: std::time::Instant) {
+        self.bytes_written.fetch_add(sz as u64, Ordering::Relaxed);
+        self.last_write.store(self.timestamp(at), Ordering::Relaxed);
+    }
+
+    fn timestamp(&self, at: std::time::Instant) -> u64 {
+        let nanos = at.saturating_duration_since(self.epoch).as_nanos();
+        u64::try_from(nanos).unwrap_or(u64::MAX - 1) + 1
+    }
+
+    fn instant(&self, timestamp: &std::sync::atomic::AtomicU64) -> Option<std::time::Instant> {
+        match timestamp.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(self.epoch + std::time::Duration::from_nanos(nanos - 1)),
+        }
+    }
+}
+
+/// Whether the worker threads of a stream are running, returned by
+/// [SerialStream::worker_status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorkerStatus {
+    /// Whether the thread reading from the port is running.
+    pub reader_alive: bool,
+    /// Whether the thread writing to the port is running.
+    pub writer_alive: bool,
+}
+
+/// The failure of a worker thread of a [SerialStream].
+///
+/// Errors returned from reading or writing a [SerialStream] which were caused
+/// by a worker thread wrap this type. Get at it with
+/// [std::io::Error::get_ref] and `downcast_ref` to learn
+/// which thread failed and why, e.g. to decide whether to retry a write or to
+/// restart the whole stream.
+///
+/// When the name of the port is known, the error message starts with it, so
+/// that failures of different streams can be told apart.
+#[derive(thiserror::Error, Debug, Clone)]
+pub struct WorkerError {
+    port: Option<String>,
+    direction: Direction,
+    error: Arc<SerialError>,
+}
+
+impl std::fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(port) = &self.port {
+            write!(f, "{port}: ")?;
+        }
+        write!(f, "{} thread: {}", self.direction, self.error)
+    }
+}
+
+impl WorkerError {
+    /// The thread which failed.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The name of the port, usually its device path, if known.
+    pub fn port(&self) -> Option<&str> {
+        self.port.as_deref()
+    }
+
+    /// Why the thread stopped.
+    pub fn error(&self) -> &SerialError {
+        &self.error
+    }
+
+    /// The [std::io::ErrorKind] this error is reported with.
+    fn kind(&self) -> std::io::ErrorKind {
+        match &*self.error {
+            SerialError::Io(e) => e.kind(),
+            SerialError::Disconnected(_)
+            | SerialError::ChannelClosed
+            | SerialError::WriterExited => std::io::ErrorKind::BrokenPipe,
+            SerialError::Closed => std::io::ErrorKind::NotConnected,
+            SerialError::Cancelled => std::io::ErrorKind::ConnectionAborted,
+            SerialError::Panicked(_) => std::io::ErrorKind::Other,
+            SerialError::CorruptInput => std::io::ErrorKind::InvalidData,
+            SerialError::Disabled => std::io::ErrorKind::Unsupported,
+            SerialError::Eof => std::io::ErrorKind::UnexpectedEof,
+        }
+    }
+}
+
+/// Why a worker thread of a [SerialStream] stopped, as carried by a
+/// [WorkerError].
+///
+/// Like those of `tokio-serial`, the methods of [SerialStream] return
+/// [std::io::Error]. Match on this type, reached with [WorkerError::error],
+/// instead of inspecting error messages. The [std::io::ErrorKind] each variant
+/// is reported with is given below.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum SerialError {
+    /// Reading from or writing to the port failed. This keeps the kind of the
+    /// original error.
+    #[error("IO error {0}")]
+    Io(#[from] std::io::Error),
+    /// The thread panicked, with the panic message. Reported as
+    /// [std::io::ErrorKind::Other].
+    #[error("panicked: {0}")]
+    Panicked(String),
+    /// The thread lost the connection to the stream, e.g. because the stream
+    /// was dropped while writing. Reported as
+    /// [std::io::ErrorKind::BrokenPipe].
+    #[error("sending channel closed")]
+    ChannelClosed,
+    /// The stream was closed, e.g. by [SerialStream::shutdown]. Reading then
+    /// reports the end of the stream; other operations fail with
+    /// [std::io::ErrorKind::NotConnected].
+    #[error("serial stream closed")]
+    Closed,
+    /// The device went away, e.g. because a USB adapter was unplugged.
+    /// Reported as [std::io::ErrorKind::BrokenPipe].
+    #[error("serial port disconnected: {0}")]
+    Disconnected(std::io::Error),
+    /// The writer thread exited while a command was waiting for it. Reported
+    /// as [std::io::ErrorKind::BrokenPipe].
+    #[error("writer thread exited")]
+    WriterExited,
+    /// The token set with [SerialPortBuilder::with_cancellation] was
+    /// cancelled. Reported as [std::io::ErrorKind::ConnectionAborted].
+    #[error("cancelled")]
+    Cancelled,
+    /// A byte was received with a parity or framing error, or a break
+    /// condition was detected, see [SerialPortBuilder::report_input_errors].
+    /// Reported as [std::io::ErrorKind::InvalidData].
+    #[error("received a byte with a parity or framing error")]
+    CorruptInput,
+    /// The stream was opened without this direction, see
+    /// [SerialPortBuilder::direction]. Reported as
+    /// [std::io::ErrorKind::Unsupported].
+    #[error("stream not opened for this direction")]
+    Disabled,
+    /// Reading from the port returned no bytes, which
+    /// [EmptyReadPolicy::Eof] treats as the end of the input. Reading then
+    /// reports the end of the stream.
+    #[error("end of input")]
+    Eof,
+}
+
+/// Get the file descriptor of the port.
+///
+/// This is meant for operations which this crate does not provide, such as
+/// custom `ioctl` calls. The reader and writer threads use the port
+/// concurrently through their own handles, so the caller must make sure that
+/// such operations do not interfere with them. Do not read from, write to or
+/// close the descriptor.
+///
+/// The descriptor is known only for streams opened with
+/// [SerialPortBuilderExt::open_native_async] and their clones. For other
+/// streams, this returns -1, which is never a valid file descriptor.
+impl std::os::unix::io::AsRawFd for SerialStream {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.raw_fd.unwrap_or(-1)
+    }
+}
+
+impl std::fmt::Debug for SerialStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialStream")
+            .field("name", &self.name)
+            .field("max_buf_size", &self.options.max_buf_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Debug for SerialReadHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialReadHalf").finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Debug for SerialWriteHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialWriteHalf")
+            .field("name", &self.name)
+            .field("write_policy", &self.write_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+// ----------- implementation details below here -----------
+
+impl tokio::io::AsyncRead for SerialStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        self.project().reader.poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncBufRead for SerialStream {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        self.project().reader.poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().reader.consume(amt)
+    }
+}
+
+impl tokio::io::AsyncWrite for SerialStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.project().writer.poll_write(cx, buf)
+    }
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.project().writer.poll_write_vectored(cx, bufs)
+    }
+    fn is_write_vectored(&self) -> bool {
+        self.writer.is_write_vectored()
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.project().writer.poll_flush(cx)
+    }
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        self.project().writer.poll_shutdown(cx)
+    }
+}
+
+impl tokio::io::AsyncRead for SerialReadHalf {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        self.discard_if_cleared();
+        let mut this = self.project();
+        if !this.peeked.is_empty() {
+            let sz = this.peeked.len().min(buf.remaining());
+            buf.put_slice(&this.peeked[..sz]);
+            this.peeked.drain(..sz);
+            return Poll::Ready(Ok(()));
+        }
+        // The port reported the end of its input, or went away with
+        // `disconnect_as_eof`.
+        let disconnect_as_eof = *this.disconnect_as_eof;
+        let is_eof = move |e: &WorkerError| match *e.error {
+            SerialError::Eof => true,
+            SerialError::Disconnected(_) => disconnect_as_eof,
+            _ => false,
+        };
+        // The stream was closed on purpose, e.g. by a failed
+        // `SerialStream::reopen`, or the input ended, both of which are
+        // reported as the end of the stream.
+        let exited = |res: Result<NeverOk, WorkerError>| match res {
+            Err(e) if matches!(*e.error, SerialError::Closed) || is_eof(&e) => Poll::Ready(Ok(())),
+            res => Poll::Ready(to_std_io(res)),
+        };
+        // The bytes the reader thread handed on before it stopped are read
+        // first, unless the stream was closed on purpose. The thread has
+        // dropped its end of the channel by then, so the channel ends.
+        if let Poll::Ready(res) = this.read_err.as_mut().poll(cx) {
+            if matches!(&res, Err(e) if matches!(*e.error, SerialError::Closed)) {
+                return exited(res);
+            }
+        }
+        let filled = buf.filled().len();
+        if let Poll::Ready(res) = this.reader_channel.poll_read(cx, buf) {
+            if res.is_ok() && buf.filled().len() == filled && buf.remaining() > 0 {
+                // The reader thread exited and dropped its end of the channel.
+                // Its result follows shortly.
+                return exited(std::task::ready!(this.read_err.poll(cx)));
+            }
+            return Poll::Ready(res);
+        }
+        this.idle.waker.register(cx.waker());
+        if this.idle.idle.swap(false, Ordering::AcqRel) {
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "no data received within the read idle timeout",
+            )));
+        }
+        match this.read_err.poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(res) => exited(res),
+        }
+    }
+}
+
+/// The bytes returned by `poll_fill_buf` are held like those returned by
+/// [SerialReadHalf::peek], so both can be mixed with reads.
+impl tokio::io::AsyncBufRead for SerialReadHalf {
+    fn poll_fill_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        self.discard_if_cleared();
+        if self.peeked.is_empty() {
+            let mut peeked = std::mem::take(&mut self.peeked);
+            peeked.resize(FILL_BUF_SIZE, 0);
+            let mut buf = ReadBuf::new(&mut peeked);
+            let res = tokio::io::AsyncRead::poll_read(self.as_mut(), cx, &mut buf);
+            let sz = buf.filled().len();
+            peeked.truncate(sz);
+            self.peeked = peeked;
+            std::task::ready!(res)?;
+        }
+        Poll::Ready(Ok(self.project().peeked.as_slice()))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let peeked = self.project().peeked;
+        peeked.drain(..amt.min(peeked.len()));
+    }
+}
+
+impl SerialReadHalf {
+    /// Receive bytes without removing them from the stream.
+    ///
+    /// This waits until at least one byte is available, like a read, and
+    /// returns how many bytes were copied to `buf`. The same bytes are
+    /// returned again by the next peek or read. If bytes were peeked before,
+    /// this returns those without receiving more, so it may return fewer bytes
+    /// than fit into `buf`.
+    pub async fn peek(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.discard_if_cleared();
+        if self.peeked.is_empty() && !buf.is_empty() {
+            let mut peeked = vec![0u8; buf.len()];
+            let sz = self.read(&mut peeked).await?;
+            peeked.truncate(sz);
+            self.peeked = peeked;
+        }
+        let sz = self.peeked.len().min(buf.len());
+        buf[..sz].copy_from_slice(&self.peeked[..sz]);
+        Ok(sz)
+    }
+
+    /// Take the bytes currently held in the internal buffer without waiting
+    /// for more.
+    ///
+    /// This returns the bytes which were received but not read yet, or an
+    /// empty vector if there are none, e.g. to parse a partial last frame
+    /// before closing the stream. Unlike reading, this never waits and never
+    /// fails, and it returns received bytes even after the reader thread
+    /// failed. Bytes the reader thread is about to hand on may arrive right
+    /// after this returns.
+    pub fn take_buffered(&mut self) -> Vec<u8> {
+        self.discard_if_cleared();
+        let mut buffered = std::mem::take(&mut self.peeked);
+        let mut chunk = [0u8; 1024];
+        while let Some(Ok(sz @ 1..)) = self.reader_channel.read(&mut chunk).now_or_never() {
+            buffered.extend_from_slice(&chunk[..sz]);
+        }
+        buffered
+    }
+
+    /// Drop the internal buffer if [SerialStream::clear] cleared the input
+    /// since the last read.
+    fn discard_if_cleared(&mut self) {
+        if self.cleared.swap(false, Ordering::AcqRel) {
+            self.discard_buffered();
+        }
+    }
+
+    /// Drop the bytes currently held in the internal buffer without waiting
+    /// for more.
+    fn discard_buffered(&mut self) {
+        self.peeked.clear();
+        let mut scratch = [0u8; 1024];
+        while let Some(Ok(1..)) = self.reader_channel.read(&mut scratch).now_or_never() {}
+    }
+
+    /// Stop the reader thread and wait for it to exit.
+    fn stop_and_join(&mut self) {
+        self.stop_reader.0.store(true, Ordering::Relaxed);
+        self.wake_reader.take();
+        if let Some(thread) = self.thread.take() {
+            // The thread may be waiting for room in the internal buffer.
+            while !thread.is_finished() {
+                self.discard_buffered();
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            thread.join();
+        }
+    }
+}
+
+impl SerialWriteHalf {
+    /// Get the number of bytes in the internal buffer which the writer thread
+    /// has not handed to the port yet.
+    ///
+    /// This is a hint only, as the writer thread keeps taking bytes.
+    pub fn write_buffer_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Wait until the writer thread has handed all bytes written so far to the
+    /// port, without waiting for the port to transmit them.
+    ///
+    /// Afterwards, [SerialWriteHalf::write_buffer_len] does not count these
+    /// bytes any more, and they are in the output queue of the driver. Unlike
+    /// a flush, such as with [tokio::io::AsyncWriteExt::flush], this does not
+    /// wait for the hardware, which can take long when flow control is in
+    /// effect. Handing bytes to the port can still block on a full output
+    /// queue. This is ordered with flushes, breaks and
+    /// [SerialStream::queue_control].
+    pub async fn flush_software(&mut self) -> std::io::Result<()> {
+        self.command(ControlCommand::HandOff).await
+    }
+
+    /// Write up to `len` bytes to the internal buffer with `write`, following
+    /// the [WritePolicy].
+    ///
+    /// `write` is passed how many bytes it may write at most.
+    fn poll_write_with(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        len: usize,
+        write: impl Fn(Pin<&mut WriteSink>, &mut Context<'_>, usize) -> Poll<std::io::Result<usize>>,
+    ) -> Poll<std::io::Result<usize>> {
+        let mut this = self.project();
+        if let Poll::Ready(res) = this.write_err.poll(cx) {
+            return Poll::Ready(to_std_io(res));
+        }
+        let drop_oldest = *this.write_policy == WritePolicy::DropOldest;
+        loop {
+            let permit = match this.inflight.as_mut() {
+                Some(inflight) => {
+                    // Take what is available rather than waiting for all of
+                    // `len`, so that partial writes proceed.
+                    let wanted = len.min(inflight.available_permits().max(1));
+                    match inflight.poll_acquire_many(cx, wanted.try_into().unwrap_or(u32::MAX)) {
+                        Poll::Ready(Some(permit)) => Some(permit),
+                        // The semaphore is never closed.
+                        Poll::Ready(None) => None,
+                        Poll::Pending if drop_oldest => {
+                            let discarded = this.queue.discard(len);
+                            if discarded == 0 {
+                                // The writer thread frees permits and wakes us.
+                                return Poll::Pending;
+                            }
+                            inflight.add_permits(discarded);
+                            continue;
+                        }
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                None => None,
+            };
+            let limit = permit.as_ref().map_or(len, |permit| permit.num_permits());
+            match write(this.writer_sink.as_mut(), cx, limit) {
+                Poll::Ready(Ok(sz)) => {
+                    this.queue.added(sz);
+                    if let Some(permit) = permit {
+                        // The writer thread returns the permits for the
+                        // written bytes once it has handed them to the port.
+                        let unused = permit.num_permits() - sz;
+                        permit.forget();
+                        if let Some(inflight) = this.inflight.as_ref() {
+                            inflight.add_permits(unused);
+                        }
+                    }
+                    return Poll::Ready(Ok(sz));
+                }
+                Poll::Pending if drop_oldest => {
+                    let discarded = this.queue.discard(len);
+                    if let Some(inflight) = this.inflight.as_ref() {
+                        inflight.add_permits(discarded);
+                    }
+                    if discarded == 0 {
+                        // The writer thread just made room and wakes us.
+                        return Poll::Pending;
+                    }
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Stop the writer thread, discarding queued bytes, and wait for it to
+    /// exit.
+    fn stop_and_join(&mut self) {
+        let _ = self.commands.send(ControlCommand::Stop);
+        if let Some(thread) = self.thread.take() {
+            thread.join();
+        }
+    }
+
+    /// Send a command to the writer thread and wait for it to be handled.
+    async fn command(
+        &self,
+        command: impl FnOnce(tokio::sync::oneshot::Sender<std::io::Result<()>>) -> ControlCommand,
+    ) -> std::io::Result<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        // If the writer thread is gone, `rx` fails below.
+        let _ = self.commands.send(command(tx));
+        match rx.await {
+            Ok(res) => res,
+            Err(_) => to_std_io(Err(WorkerError {
+                port: self.name.clone(),
+                direction: Direction::Write,
+                error: Arc::new(SerialError::WriterExited),
+            })),
+        }
+    }
+}
+
+impl tokio::io::AsyncWrite for SerialWriteHalf {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.poll_write_with(cx, buf.len(), |sink, cx, limit| {
+            sink.poll_write(cx, &buf[..limit])
+        })
+    }
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+        self.poll_write_with(cx, len, |sink, cx, limit| {
+            if limit == len {
+                return sink.poll_write_vectored(cx, bufs);
+            }
+            // Write as much of the first slice as is allowed.
+            let buf = bufs
+                .iter()
+                .find(|buf| !buf.is_empty())
+                .map_or(&[][..], |buf| &buf[..buf.len().min(limit)]);
+            sink.poll_write(cx, buf)
+        })
+    }
+    fn is_write_vectored(&self) -> bool {
+        self.writer_sink.is_write_vectored()
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        let mut this = self.project();
+        if let Poll::Ready(res) = this.write_err.as_mut().poll(cx) {
+            return Poll::Ready(to_std_io(res));
+        }
+        if let Poll::Ready(Err(e)) = this.writer_sink.poll_flush(cx) {
+            return Poll::Ready(Err(e));
+        }
+        let flush_done = this.flush_done.get_or_insert_with(|| {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            // If the writer thread is gone, `rx` fails below.
+            let _ = this.commands.send(ControlCommand::Flush(tx));
+            rx
+        });
+        match flush_done.poll_unpin(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(res)) => {
+                *this.flush_done = None;
+                Poll::Ready(res)
+            }
+            Poll::Ready(Err(_)) => {
+                // The writer thread exited. Report its error once it arrives.
+                *this.flush_done = None;
+                this.write_err.poll(cx).map(to_std_io)
+            }
+        }
+    }
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut this = self.project();
+        if this.write_err.as_mut().poll(cx).is_pending() {
+            std::task::ready!(this.writer_sink.poll_shutdown(cx))?;
+        }
+        // The writer thread exits once it has handed the remaining bytes to
+        // the port and found the end of the written data.
+        this.write_err.poll(cx).map(|res| match res {
+            Err(e) if matches!(e.error(), SerialError::ChannelClosed) => Ok(()),
+            res => to_std_io(res),
+        })
+    }
+}
+
+impl SerialStream {
+    /// The size of the buffer the reader thread fills.
+    pub(crate) fn read_buf_size(&self) -> usize {
+        self.options.read_buf_size()
+    }
+
+    /// Run `f` on the file descriptor of the port, failing if it is not known.
+    fn with_fd<T>(
+        &self,
+        f: impl FnOnce(std::os::unix::io::RawFd) -> std::io::Result<T>,
+    ) -> std::io::Result<T> {
+        let raw_fd = self.raw_fd.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "this needs the file descriptor of the port",
+            )
+        })?;
+        // The control handle keeps `raw_fd` open while it is locked.
+        let port = lock(&self.control);
+        if port.is_none() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                SerialError::Closed,
+            ));
+        }
+        f(raw_fd)
+    }
+
+    /// Run `f` on the handle used for control operations.
+    ///
+    /// This is a separate clone of the port, so control operations do not wait
+    /// on the reader or writer threads.
+    fn with_control<T>(
+        &self,
+        f: impl FnOnce(&mut dyn SerialPort) -> serialport::Result<T>,
+    ) -> std::io::Result<T> {
+        let mut port = lock(&self.control);
+        match port.as_mut() {
+            Some(port) => Ok(f(port.as_mut())?),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                SerialError::Closed,
+            )),
+        }
+    }
+}
+
+/// Lock `mutex`, ignoring poisoning. The values protected here stay valid if a
+/// thread panics while holding the lock.
+pub(crate) fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// The internal buffer for writing, as seen from the writer thread.
+///
+/// This is shared with [SerialWriteHalf], which can discard queued bytes, see
+/// [WritePolicy::DropOldest].
+struct WriteQueue {
+    reader: std::sync::Mutex<WriteQueueReader>,
+    /// The number of queued bytes. Bytes may be taken before they were counted
+    /// as added, so this is briefly negative at times.
+    len: std::sync::atomic::AtomicIsize,
+}
+
+struct WriteQueueReader {
+    source: WriteSource,
+    /// The waker of the writer thread when it last waited for bytes.
+    waker: Option<std::task::Waker>,
+}
+
+impl WriteQueue {
+    fn new(source: WriteSource) -> Self {
+        Self {
+            reader: std::sync::Mutex::new(WriteQueueReader {
+                source,
+                waker: None,
+            }),
+            len: std::sync::atomic::AtomicIsize::new(0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed).max(0) as usize
+    }
+
+    /// Count `sz` bytes written to the sink.
+    fn added(&self, sz: usize) {
+        self.len.fetch_add(sz as isize, Ordering::Relaxed);
+    }
+
+    /// Take bytes for the writer thread.
+    fn poll_take(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        let mut reader = lock(&self.reader);
+        if !reader
+            .waker
+            .as_ref()
+            .is_some_and(|waker| waker.will_wake(cx.waker()))
+        {
+            reader.waker = Some(cx.waker().clone());
+        }
+        self.poll_read(&mut reader, cx, buf)
+    }
+
+    /// Drop up to `max` of the oldest queued bytes and return how many.
+    fn discard(&self, max: usize) -> usize {
+        let mut reader = lock(&self.reader);
+        // Should the queue turn out to be empty, the source keeps the waker it
+        // was polled with, which must be the one of the writer thread.
+        let waker = reader
+            .waker
+            .clone()
+            .unwrap_or_else(|| futures::task::noop_waker_ref().clone());
+        let mut scratch = [0u8; 1024];
+        let len = max.clamp(1, scratch.len());
+        match self.poll_read(
+            &mut reader,
+            &mut Context::from_waker(&waker),
+            &mut scratch[..len],
+        ) {
+            Poll::Ready(Ok(sz)) => sz,
+            _ => 0,
+        }
+    }
+
+    fn poll_read(
+        &self,
+        reader: &mut WriteQueueReader,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let mut read_buf = ReadBuf::new(buf);
+        std::task::ready!(tokio::io::AsyncRead::poll_read(
+            Pin::new(&mut reader.source),
+            cx,
+            &mut read_buf
+        ))?;
+        let sz = read_buf.filled().len();
+        self.len.fetch_sub(sz as isize, Ordering::Relaxed);
+        Poll::Ready(Ok(sz))
+    }
+}
+
+/// Where [SerialWriteHalf] puts written bytes for the writer thread.
+enum WriteSink {
+    /// A buffer of limited size.
+    Duplex(tokio::io::DuplexStream),
+    /// A channel without limit, with [SerialPortBuilder::unbounded_write_buffer].
+    /// `None` once shut down.
+    Unbounded(Option<tokio::sync::mpsc::UnboundedSender<tokio_util::bytes::Bytes>>),
+}
+
+impl WriteSink {
+    /// Create a sink and the source which the writer thread reads from.
+    fn new(buf_size: Option<usize>) -> (WriteSink, WriteSource) {
+        match buf_size {
+            Some(buf_size) => {
+                let (source, sink) = tokio::io::duplex(buf_size);
+                (WriteSink::Duplex(sink), WriteSource::Duplex(source))
+            }
+            None => {
+                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                (
+                    WriteSink::Unbounded(Some(tx)),
+                    WriteSource::Unbounded(rx, Default::default()),
+                )
+            }
+        }
+    }
+
+    fn send(&mut self, chunk: tokio_util::bytes::Bytes) -> std::io::Result<usize> {
+        let len = chunk.len();
+        match self {
+            WriteSink::Unbounded(Some(tx)) if tx.send(chunk).is_ok() => Ok(len),
+            _ => Err(std::io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+impl tokio::io::AsyncWrite for WriteSink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+        match this {
+            WriteSink::Duplex(duplex) => Pin::new(duplex).poll_write(cx, buf),
+            WriteSink::Unbounded(_) => {
+                Poll::Ready(this.send(tokio_util::bytes::Bytes::copy_from_slice(buf)))
+            }
+        }
+    }
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = Pin::into_inner(self);
+        match this {
+            WriteSink::Duplex(duplex) => Pin::new(duplex).poll_write_vectored(cx, bufs),
+            WriteSink::Unbounded(_) => {
+                let chunk: Vec<u8> = bufs.iter().flat_map(|buf| buf.iter().copied()).collect();
+                Poll::Ready(this.send(chunk.into()))
+            }
+        }
+    }
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            WriteSink::Duplex(duplex) => duplex.is_write_vectored(),
+            WriteSink::Unbounded(_) => true,
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match Pin::into_inner(self) {
+            WriteSink::Duplex(duplex) => Pin::new(duplex).poll_flush(cx),
+            WriteSink::Unbounded(_) => Poll::Ready(Ok(())),
+        }
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match Pin::into_inner(self) {
+            WriteSink::Duplex(duplex) => Pin::new(duplex).poll_shutdown(cx),
+            WriteSink::Unbounded(tx) => {
+                tx.take();
+                Poll::Ready(Ok(()))
+            }
+        }
+    }
+}
+
+/// The other end of a [WriteSink].
+enum WriteSource {
+    Duplex(tokio::io::DuplexStream),
+    /// The channel and the rest of the chunk last received from it.
+    Unbounded(
+        tokio::sync::mpsc::UnboundedReceiver<tokio_util::bytes::Bytes>,
+        tokio_util::bytes::Bytes,
+    ),
+}
+
+impl tokio::io::AsyncRead for WriteSource {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match Pin::into_inner(self) {
+            WriteSource::Duplex(duplex) => Pin::new(duplex).poll_read(cx, buf),
+            WriteSource::Unbounded(rx, pending) => {
+                if pending.is_empty() {
+                    match std::task::ready!(rx.poll_recv(cx)) {
+                        Some(chunk) => *pending = chunk,
+                        // The sink was shut down or dropped.
+                        None => return Poll::Ready(Ok(())),
+                    }
+                }
+                let sz = pending.len().min(buf.remaining());
+                buf.put_slice(&pending.split_to(sz));
+                Poll::Ready(Ok(()))
+            }
+        }
+    }
+}
 
-// ensure that we never instantiate a NeverOk type
-macro_rules! assert_never {
-    ($never: expr) => {{
-        let _: NeverOk = $never;
-        unreachable!("NeverOk was instantiated");
-    }};
+/// Requests handled by the writer thread in between writes.
+///
+/// All operations which must be ordered relative to written bytes go through
+/// this, so that they are handled in the order they were sent, after the
+/// bytes written before them.
+enum ControlCommand {
+    /// Write everything queued so far, wait until the port has transmitted it
+    /// and report the result.
+    Flush(tokio::sync::oneshot::Sender<std::io::Result<()>>),
+    /// Write everything queued so far and report the result, without waiting
+    /// for the port to transmit it.
+    HandOff(tokio::sync::oneshot::Sender<std::io::Result<()>>),
+    /// Write everything queued so far, wait until it was transmitted, then
+    /// hold a break condition for the given duration and report the result.
+    Break(
+        std::time::Duration,
+        tokio::sync::oneshot::Sender<std::io::Result<()>>,
+    ),
+    /// Write everything queued so far, wait until it was transmitted and
+    /// report the result, then write nothing more until the receiver resolves
+    /// or its sender is dropped. Meanwhile, the port can be reconfigured
+    /// through another handle.
+    Pause(
+        tokio::sync::oneshot::Sender<std::io::Result<()>>,
+        tokio::sync::oneshot::Receiver<()>,
+    ),
+    /// Exit without writing anything still queued.
+    Stop,
 }
 
-/// Builder to open a serial port.
+/// Reports the first error of `inner` as the end of the data, keeping the
+/// error for later.
 ///
-/// Create this by calling [new]. Open the port by calling
-/// [SerialPortBuilderExt::open_native_async].
-pub struct SerialPortBuilder {
-    path: String,
-    baud_rate: u32,
-    max_buf_size: usize,
+/// This lets a decoder see the data received before the error.
+struct EofOnError<R> {
+    inner: R,
+    error: Option<std::io::Error>,
+    failed: bool,
 }
 
-/// Create a [SerialPortBuilder] from a device path and a baud rate.
-pub fn new<'a>(path: impl Into<std::borrow::Cow<'a, str>>, baud_rate: u32) -> SerialPortBuilder {
-    SerialPortBuilder {
-        path: path.into().into_owned(),
-        baud_rate,
-        max_buf_size: 1024,
+impl<R: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for EofOnError<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        if self.failed {
+            return Poll::Ready(Ok(()));
+        }
+        match Pin::new(&mut self.inner).poll_read(cx, buf) {
+            Poll::Ready(Err(e)) => {
+                self.error = Some(e);
+                self.failed = true;
+                Poll::Ready(Ok(()))
+            }
+            res => res,
+        }
     }
 }
 
-impl SerialPortBuilder {
-    /// Set the maximum buffer size in the internal buffer.
-    pub fn max_buf_size(self, max_buf_size: usize) -> Self {
-        Self {
-            max_buf_size,
-            ..self
+/// Convert an error from decoding lines to a [std::io::Error].
+fn lines_error(e: tokio_util::codec::LinesCodecError) -> std::io::Error {
+    match e {
+        tokio_util::codec::LinesCodecError::Io(e) => e,
+        e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Set by the reader thread when no data arrived for the read idle timeout.
+#[derive(Default)]
+struct IdleSignal {
+    timeout: Option<std::time::Duration>,
+    idle: AtomicBool,
+    waker: futures::task::AtomicWaker,
+}
+
+impl IdleSignal {
+    fn set(&self, idle: bool) {
+        self.idle.store(idle, Ordering::Release);
+        if idle {
+            self.waker.wake();
         }
     }
 }
 
-/// Provides a convenience function for maximum compatibility with `tokio-serial`.
-pub trait SerialPortBuilderExt {
-    /// Open a serial port and return it as a [SerialStream].
-    fn open_native_async(self) -> std::io::Result<SerialStream>;
+/// Sets a flag when dropped, e.g. to tell a worker thread to exit.
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Release);
+    }
 }
 
-impl SerialPortBuilderExt for SerialPortBuilder {
-    fn open_native_async(self) -> std::io::Result<SerialStream> {
-        let port = serialport::new(self.path, self.baud_rate).open()?;
-        open(port, self.max_buf_size)
+/// Launches the body of a worker thread, set with
+/// [SerialPortBuilder::with_thread_spawner].
+#[derive(Clone)]
+struct ThreadSpawner(Arc<dyn Fn(ThreadBody) -> std::io::Result<()> + Send + Sync>);
+
+/// The body of a worker thread.
+type ThreadBody = Box<dyn FnOnce() + Send>;
+
+impl std::fmt::Debug for ThreadSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ThreadSpawner")
     }
 }
 
-pin_project! {
-    /// An asynchronous implementation of a serial port.
-    ///
-    /// Implements both [tokio::io::AsyncRead] and [tokio::io::AsyncWrite].
-    ///
-    /// This could be wrapped with
-    /// [`tokio_util::codec::Framed`](https://docs.rs/tokio-util/0.7.11/tokio_util/codec/struct.Framed.html),
-    /// for example.
-    pub struct SerialStream {
-        #[pin]
-        read_err: Pin<Box<dyn Future<Output = Result<NeverOk, Error>> + Send>>,
-        #[pin]
-        write_err: Pin<Box<dyn Future<Output = Result<NeverOk, Error>> + Send>>,
-        #[pin]
-        reader_duplex: tokio::io::ReadHalf<tokio::io::DuplexStream>,
-        #[pin]
-        writer_duplex: tokio::io::WriteHalf<tokio::io::DuplexStream>,
+/// Observes the bytes passing through the port, set with
+/// [SerialPortBuilder::with_tracer].
+#[derive(Clone)]
+struct Tracer(Arc<TraceFn>);
+
+/// The function called by a [Tracer].
+type TraceFn = dyn Fn(Direction, &[u8]) + Send + Sync;
+
+impl std::fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Tracer")
     }
 }
 
-// ----------- implementation details below here -----------
+/// Pass `buf` to `tracer`, if any.
+fn trace(tracer: &Option<Tracer>, direction: Direction, buf: &[u8]) {
+    if let Some(tracer) = tracer {
+        (tracer.0)(direction, buf);
+    }
+}
 
-impl tokio::io::AsyncRead for SerialStream {
-    fn poll_read(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &mut ReadBuf<'_>,
-    ) -> Poll<Result<(), std::io::Error>> {
-        let this = self.project();
-        match this.read_err.poll(cx) {
-            Poll::Pending => this.reader_duplex.poll_read(cx, buf),
-            Poll::Ready(res) => Poll::Ready(to_std_io(res)),
+/// A running worker thread.
+struct WorkerThread {
+    /// Set once the body of the thread has returned or was dropped.
+    finished: Arc<AtomicBool>,
+    /// The handle, unless the thread was launched by a [ThreadSpawner].
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl WorkerThread {
+    /// Launch `body` on a new thread, or with `spawner` if set.
+    fn spawn(
+        direction: Direction,
+        port: Option<&str>,
+        spawner: Option<&ThreadSpawner>,
+        body: impl FnOnce() + Send + 'static,
+    ) -> std::io::Result<Self> {
+        let finished = Arc::new(AtomicBool::new(false));
+        let on_finish = SetOnDrop(finished.clone());
+        let body = move || {
+            let _on_finish = on_finish;
+            body();
+        };
+        let handle = match spawner {
+            None => Some(worker_thread(direction, port).spawn(body)?),
+            Some(spawner) => {
+                (spawner.0)(Box::new(body))?;
+                None
+            }
+        };
+        Ok(Self { finished, handle })
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Wait for the thread to exit.
+    fn join(self) {
+        match self.handle {
+            Some(handle) => {
+                let _ = handle.join();
+            }
+            None => {
+                while !self.is_finished() {
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+            }
         }
     }
 }
 
-impl tokio::io::AsyncWrite for SerialStream {
-    fn poll_write(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &[u8],
-    ) -> Poll<Result<usize, std::io::Error>> {
-        let this = self.project();
-        match this.write_err.poll(cx) {
-            Poll::Pending => this.writer_duplex.poll_write(cx, buf),
-            Poll::Ready(res) => Poll::Ready(to_std_io(res)),
+/// Resolves to the outcome of a worker thread.
+///
+/// Unlike most futures, this may be polled again after it completed, and then
+/// repeats the outcome. This way, every read or write after a worker thread
+/// failed reports the failure. Each connection has its own worker threads, so
+/// [SerialStream::reopen] resets the outcome by replacing this as a whole.
+struct WorkerResult {
+    future: Pin<Box<dyn Future<Output = Result<NeverOk, WorkerError>> + Send>>,
+    done: Option<Result<NeverOk, WorkerError>>,
+}
+
+impl WorkerResult {
+    fn new(future: impl Future<Output = Result<NeverOk, WorkerError>> + Send + 'static) -> Self {
+        Self {
+            future: Box::pin(future),
+            done: None,
         }
     }
-    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
-        let this = self.project();
-        match this.write_err.poll(cx) {
-            Poll::Pending => this.writer_duplex.poll_flush(cx),
-            Poll::Ready(res) => Poll::Ready(to_std_io(res)),
+}
+
+impl Future for WorkerResult {
+    type Output = Result<NeverOk, WorkerError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(done) = &self.done {
+            return Poll::Ready(done.clone());
         }
+        let res = std::task::ready!(self.future.as_mut().poll(cx));
+        self.done = Some(res.clone());
+        Poll::Ready(res)
     }
-    fn poll_shutdown(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Result<(), std::io::Error>> {
-        let this = self.project();
-        match this.write_err.poll(cx) {
-            Poll::Pending => this.writer_duplex.poll_shutdown(cx),
-            Poll::Ready(res) => Poll::Ready(to_std_io(res)),
+}
+
+/// Whether a worker thread stopped because it was told to or because the
+/// stream was configured that way, as opposed to failing, see
+/// [SerialStream::close].
+fn is_clean_exit(e: &WorkerError) -> bool {
+    match &*e.error {
+        SerialError::Closed
+        | SerialError::Eof
+        | SerialError::ChannelClosed
+        | SerialError::Cancelled
+        | SerialError::Disabled => true,
+        // A read timeout ends the reader thread by design, while a write
+        // timeout means that bytes were not transmitted.
+        SerialError::Io(io) => {
+            e.direction == Direction::Read && io.kind() == std::io::ErrorKind::TimedOut
         }
+        _ => false,
     }
 }
 
 /// A zero-sized type which is never created to indicate that Ok(_) never
 /// happens.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum NeverOk {}
 
-#[derive(thiserror::Error, Debug)]
-enum Error {
-    #[error("IO error {0}")]
-    Io(#[from] std::io::Error),
-    #[error("sending thread paniced {0}")]
-    OneshotRecv(tokio::sync::oneshot::error::RecvError),
-    #[error("sending channel closed")]
-    SenderClosed,
+/// Classify an error returned by the serial port itself.
+///
+/// A hung up tty, which is what an unplugged USB adapter looks like, is
+/// reported by `serialport` as [std::io::ErrorKind::BrokenPipe]. A device which
+/// vanished entirely shows up as [std::io::ErrorKind::NotFound]. Some drivers
+/// fail with `EIO` or `ENXIO` instead once the device is gone.
+fn port_error(e: std::io::Error) -> SerialError {
+    match e.kind() {
+        std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::NotFound => {
+            SerialError::Disconnected(e)
+        }
+        _ if matches!(e.raw_os_error(), Some(libc::EIO | libc::ENXIO)) => {
+            SerialError::Disconnected(e)
+        }
+        _ => SerialError::Io(e),
+    }
+}
+
+/// How the reader thread reads from the port and hands received bytes on.
+#[derive(Debug, Clone)]
+struct ReadChunks {
+    /// The most bytes to read from the port at once.
+    size: usize,
+    /// How long to collect bytes before handing them on.
+    coalesce_delay: std::time::Duration,
+    /// How many bytes to collect before handing them on, at least one.
+    coalesce_size: usize,
+    /// Whether the port marks bytes received with errors, see
+    /// [mark_input_errors].
+    marked_errors: bool,
+    empty_read_policy: EmptyReadPolicy,
+    tracer: Option<Tracer>,
+}
+
+/// How the writer thread hands bytes to the port.
+#[derive(Debug)]
+struct WriteChunks {
+    /// The most bytes to write to the port at once.
+    size: usize,
+    /// How long to wait between the parts of a split write.
+    pause: std::time::Duration,
+    /// Receives a permit for each byte handed to the port, with
+    /// [SerialPortBuilder::max_inflight_write].
+    inflight: Option<Arc<tokio::sync::Semaphore>>,
+    /// Counts the bytes written.
+    counters: Arc<SerialCounters>,
+    tracer: Option<Tracer>,
+}
+
+/// What tells the reader thread to stop.
+struct ReaderStop {
+    /// Set when the stream is closed.
+    closed: Arc<AtomicBool>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+    /// What to wait on with [SerialPortBuilder::nonblocking_io].
+    ready: Option<ReadyPoll>,
 }
 
-/// Read loop, launched on own thread. Returns only on error.
+/// Waits until the port has data or the reading half wakes the reader thread.
+struct ReadyPoll {
+    /// A duplicate of the descriptor of the port, so that it stays valid for
+    /// as long as the reader thread runs.
+    port: std::os::fd::OwnedFd,
+    /// Becomes readable when the reading half drops the other end.
+    woken: std::os::unix::net::UnixStream,
+}
+
+impl ReadyPoll {
+    /// Wait for up to `timeout`, returning whether the port has data, reports
+    /// an error, or the reader thread was woken.
+    fn wait(&self, timeout: std::time::Duration) -> std::io::Result<bool> {
+        use std::os::fd::AsRawFd;
+        let mut fds = [self.port.as_raw_fd(), self.woken.as_raw_fd()].map(|fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        // Rounded up, so that short timeouts do not turn into busy waiting.
+        let timeout = timeout.as_micros().div_ceil(1000).try_into().unwrap_or(-1);
+        loop {
+            // SAFETY: `fds` is valid for writes of `fds.len()` entries.
+            match unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } {
+                -1 => {
+                    let e = std::io::Error::last_os_error();
+                    if e.kind() != std::io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(true),
+            }
+        }
+    }
+}
+
+impl ReaderStop {
+    /// Fail if the reader thread should stop.
+    fn check(&self) -> Result<(), SerialError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(SerialError::Closed);
+        }
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+        {
+            return Err(SerialError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Read loop, launched on own thread. Returns only on error or when told to
+/// `stop`.
+///
+/// The port is read with a short timeout so that `stop` is noticed promptly.
+/// A user-supplied `timeout` is enforced here rather than by the port, as is
+/// the read idle timeout, which is reported through `idle`. Changes of
+/// `timeout` are picked up between reads. Received bytes are counted in
+/// `counters`.
 fn reader(
     mut port: Box<dyn SerialPort>,
-    mut tx: tokio::io::WriteHalf<tokio::io::DuplexStream>,
-) -> Result<NeverOk, Error> {
-    let mut buffer = vec![0u8; 1024];
+    mut tx: ReaderSender,
+    stop: ReaderStop,
+    shared_timeout: Arc<std::sync::Mutex<Option<std::time::Duration>>>,
+    idle: Arc<IdleSignal>,
+    chunks: ReadChunks,
+    counters: Arc<SerialCounters>,
+) -> Result<NeverOk, SerialError> {
+    let read_idle_timeout = idle.timeout;
+    // Room for a read on top of bytes not yet handed on, of which there are
+    // fewer than `coalesce_size`.
+    #[cfg(not(feature = "bytes"))]
+    let mut buffer = vec![0u8; chunks.size + chunks.coalesce_size - 1];
+    #[cfg(feature = "bytes")]
+    let mut buffer = ReadBuffer::default();
+    // The number of bytes at the start of `buffer` not yet handed on.
+    let mut pending = 0;
+    // How much of an error mark was received at the end of the last read.
+    let mut marker = 0;
+    // When the first of the pending bytes arrived.
+    let mut pending_since = std::time::Instant::now();
+    let mut timeout = *lock(&shared_timeout);
+    // When the last data arrived or `timeout` was changed.
+    let mut last_data = std::time::Instant::now();
+    // When the last data arrived or idleness was last reported.
+    let mut idle_since = last_data;
     loop {
-        let sz = port.read(&mut buffer)?;
-        futures::executor::block_on(tx.write_all(&buffer[..sz]))?;
+        stop.check()?;
+        let new_timeout = *lock(&shared_timeout);
+        if new_timeout != timeout {
+            timeout = new_timeout;
+            last_data = std::time::Instant::now();
+        }
+        if let Some(read_idle_timeout) = read_idle_timeout {
+            if idle_since.elapsed() >= read_idle_timeout {
+                idle.set(true);
+                idle_since = std::time::Instant::now();
+            }
+        }
+        let mut poll_interval = match &stop.ready {
+            // Only cancellation needs to be checked for regularly.
+            Some(_) if stop.cancel.is_none() => FOREVER,
+            _ => READ_POLL_INTERVAL,
+        };
+        if let Some(timeout) = timeout {
+            poll_interval = poll_interval.min(timeout.saturating_sub(last_data.elapsed()));
+        }
+        if let Some(read_idle_timeout) = read_idle_timeout {
+            poll_interval =
+                poll_interval.min(read_idle_timeout.saturating_sub(idle_since.elapsed()));
+        }
+        if pending > 0 {
+            poll_interval = poll_interval.min(
+                chunks
+                    .coalesce_delay
+                    .saturating_sub(pending_since.elapsed()),
+            );
+        }
+        let ready = match &stop.ready {
+            Some(ready) => {
+                let ready = ready.wait(poll_interval).map_err(port_error)?;
+                stop.check()?;
+                // Another handle of the port may take the data first.
+                poll_interval = poll_interval.min(READ_POLL_INTERVAL);
+                ready
+            }
+            None => true,
+        };
+        port.set_timeout(poll_interval)
+            .map_err(|e| port_error(e.into()))?;
+        #[cfg(not(feature = "bytes"))]
+        let res = match ready {
+            true => port.read(&mut buffer[pending..pending + chunks.size]),
+            false => Err(std::io::ErrorKind::TimedOut.into()),
+        };
+        #[cfg(feature = "bytes")]
+        let res = match ready {
+            true => {
+                buffer.resize(pending + chunks.size);
+                let res = port.read(&mut buffer[pending..]);
+                buffer.truncate(pending + *res.as_ref().unwrap_or(&0));
+                res
+            }
+            false => Err(std::io::ErrorKind::TimedOut.into()),
+        };
+        match res {
+            Ok(0) => match chunks.empty_read_policy {
+                EmptyReadPolicy::Retry => std::thread::sleep(EMPTY_READ_BACKOFF),
+                EmptyReadPolicy::Eof => {
+                    if pending > 0 {
+                        send_chunk(&mut tx, &mut buffer, pending)?;
+                    }
+                    return Err(SerialError::Eof);
+                }
+            },
+            Ok(mut sz) => {
+                last_data = std::time::Instant::now();
+                idle_since = last_data;
+                idle.set(false);
+                counters.count_read(sz, last_data);
+                trace(
+                    &chunks.tracer,
+                    Direction::Read,
+                    &buffer[pending..pending + sz],
+                );
+                if chunks.marked_errors {
+                    let corrupt;
+                    (sz, corrupt) = unmark_errors(&mut buffer[pending..pending + sz], &mut marker);
+                    if corrupt {
+                        // The reading half returns these before the error.
+                        if pending + sz > 0 {
+                            let _ = send_chunk(&mut tx, &mut buffer, pending + sz);
+                        }
+                        return Err(SerialError::CorruptInput);
+                    }
+                    #[cfg(feature = "bytes")]
+                    buffer.truncate(pending + sz);
+                }
+                if pending == 0 && sz > 0 {
+                    pending_since = last_data;
+                }
+                pending += sz;
+            }
+            Err(e) => {
+                // With `O_NONBLOCK`, another handle of the port may have taken
+                // the data, so the port is polled again.
+                let fatal = !matches!(
+                    e.kind(),
+                    std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                ) || timeout.is_some_and(|timeout| last_data.elapsed() >= timeout);
+                if fatal {
+                    if pending > 0 {
+                        // Hand on what arrived before the failure.
+                        let _ = send_chunk(&mut tx, &mut buffer, pending);
+                    }
+                    return Err(match e.kind() {
+                        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+                            std::io::Error::from(std::io::ErrorKind::TimedOut).into()
+                        }
+                        _ => port_error(e),
+                    });
+                }
+            }
+        }
+        if pending > 0
+            && (pending >= chunks.coalesce_size || pending_since.elapsed() >= chunks.coalesce_delay)
+        {
+            send_chunk(&mut tx, &mut buffer, pending)?;
+            pending = 0;
+        }
+    }
+}
+
+/// Remove the marks the port adds with `PARMRK` from `buf` in place.
+///
+/// Returns how many bytes are left at the start of `buf` and whether a byte
+/// with an error was found, in which case the bytes left are those before it.
+/// `marker` keeps track of a mark split across reads.
+fn unmark_errors(buf: &mut [u8], marker: &mut u8) -> (usize, bool) {
+    let mut sz = 0;
+    for i in 0..buf.len() {
+        let byte = buf[i];
+        match (*marker, byte) {
+            (0, 0xFF) => *marker = 1,
+            (1, 0x00) => *marker = 2,
+            // `\xFF\x00` is followed by the byte with an error, or zero for a
+            // break.
+            (2, _) => {
+                *marker = 0;
+                return (sz, true);
+            }
+            // Includes the second byte of `\xFF\xFF`, an escaped `\xFF`.
+            _ => {
+                *marker = 0;
+                buf[sz] = byte;
+                sz += 1;
+            }
+        }
+    }
+    (sz, false)
+}
+
+/// Configure the port to mark bytes received with parity or framing errors,
+/// see [SerialPortBuilder::report_input_errors].
+fn mark_input_errors(fd: std::os::unix::io::RawFd) -> std::io::Result<()> {
+    update_termios(fd, |termios| {
+        termios.c_iflag |= libc::INPCK | libc::PARMRK;
+        termios.c_iflag &= !(libc::IGNPAR | libc::IGNBRK | libc::ISTRIP);
+    })
+}
+
+/// Stop the terminal `fd` refers to from processing the data passing through,
+/// see [SerialPortBuilder::force_raw_mode].
+fn make_raw(fd: std::os::unix::io::RawFd) -> std::io::Result<()> {
+    update_termios(fd, |termios| {
+        let mut raw = *termios;
+        // SAFETY: `raw` is a valid termios structure.
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // `cfmakeraw` also clears the flag for software flow control, and
+        // changes the line settings, which are kept.
+        let flow_control = termios.c_iflag & (libc::IXON | libc::IXOFF | libc::IXANY);
+        termios.c_iflag = raw.c_iflag | flow_control;
+        termios.c_oflag = raw.c_oflag;
+        termios.c_lflag = raw.c_lflag;
+    })
+}
+
+/// Change the termios settings of the terminal `fd` refers to with `f`.
+fn update_termios(
+    fd: std::os::unix::io::RawFd,
+    f: impl FnOnce(&mut libc::termios),
+) -> std::io::Result<()> {
+    // SAFETY: `termios` is plain data which `tcgetattr` fills in.
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    // SAFETY: `fd` is an open descriptor and `termios` is valid for writes.
+    if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    f(&mut termios);
+    // SAFETY: As above, and `termios` was filled in by `tcgetattr`.
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Set `O_NONBLOCK` on the open file `fd` refers to, which all its
+/// descriptors share.
+fn set_nonblocking(fd: std::os::unix::io::RawFd) -> std::io::Result<()> {
+    // SAFETY: `fd` is an open descriptor.
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    // SAFETY: As above.
+    if flags == -1 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The reading end of the channel from the reader thread to [SerialReadHalf].
+#[cfg(not(feature = "bytes"))]
+type ReaderChannel = tokio::io::DuplexStream;
+
+/// The end of the channel the reader thread sends into.
+#[cfg(not(feature = "bytes"))]
+type ReaderSender = tokio::io::DuplexStream;
+
+/// Create the channel from the reader thread, buffering up to `buf_size`
+/// bytes.
+#[cfg(not(feature = "bytes"))]
+fn reader_channel(buf_size: usize, _read_chunk_size: usize) -> (ReaderSender, ReaderChannel) {
+    tokio::io::duplex(buf_size)
+}
+
+/// Hand the first `sz` bytes of `buffer` to the reading half.
+///
+/// This fails with [SerialError::ChannelClosed] once the reading half is gone.
+#[cfg(not(feature = "bytes"))]
+fn send_chunk(tx: &mut ReaderSender, buffer: &mut [u8], sz: usize) -> Result<(), SerialError> {
+    use tokio::io::AsyncWriteExt;
+    futures::executor::block_on(tx.write_all(&buffer[..sz])).map_err(|_| SerialError::ChannelClosed)
+}
+
+/// The end of the channel the reader thread sends into.
+#[cfg(feature = "bytes")]
+type ReaderSender = tokio::sync::mpsc::Sender<bytes::Bytes>;
+
+/// Create the channel from the reader thread, buffering up to `buf_size`
+/// bytes in chunks of at most `read_chunk_size` bytes.
+#[cfg(feature = "bytes")]
+fn reader_channel(buf_size: usize, read_chunk_size: usize) -> (ReaderSender, ReaderChannel) {
+    let (tx, rx) = tokio::sync::mpsc::channel((buf_size / read_chunk_size.max(1)).max(1));
+    (
+        tx,
+        ReaderChannel {
+            rx,
+            pending: bytes::Bytes::new(),
+        },
+    )
+}
+
+/// Hand the first `sz` bytes of `buffer` to the reading half without copying
+/// them.
+///
+/// This fails with [SerialError::ChannelClosed] once the reading half is gone.
+#[cfg(feature = "bytes")]
+fn send_chunk(
+    tx: &mut ReaderSender,
+    buffer: &mut ReadBuffer,
+    sz: usize,
+) -> Result<(), SerialError> {
+    let chunk = buffer.split_to(sz);
+    tx.blocking_send(chunk)
+        .map_err(|_| SerialError::ChannelClosed)
+}
+
+/// The buffer the reader thread reads into.
+///
+/// Chunks are split off it as [bytes::Bytes], and the memory after them is
+/// read into next. Since reads from the port need initialized memory, it
+/// remembers how much of that was written before, so that each byte of an
+/// allocation is zeroed at most once rather than on every read.
+#[cfg(feature = "bytes")]
+#[derive(Default)]
+struct ReadBuffer {
+    bytes: bytes::BytesMut,
+    /// How many bytes from the start of `bytes`, including spare capacity, are
+    /// initialized. This is never less than its length.
+    initialized: usize,
+}
+
+#[cfg(feature = "bytes")]
+impl ReadBuffer {
+    /// Change the length to `len`, zeroing only bytes never written before.
+    fn resize(&mut self, len: usize) {
+        if len > self.bytes.capacity() {
+            self.bytes.reserve(len - self.bytes.len());
+            // Only the bytes within the length are kept when reallocating.
+            self.initialized = self.bytes.len();
+        }
+        if len <= self.initialized {
+            // SAFETY: `len` is within the capacity, and the bytes up to it are
+            // initialized.
+            unsafe { self.bytes.set_len(len) };
+        } else {
+            // SAFETY: as above, with `initialized`, which is within the
+            // capacity since `len` is.
+            unsafe { self.bytes.set_len(self.initialized) };
+            self.bytes.resize(len, 0);
+            self.initialized = len;
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.bytes.truncate(len);
+    }
+
+    /// Split off the first `sz` bytes and discard the rest.
+    fn split_to(&mut self, sz: usize) -> bytes::Bytes {
+        let chunk = self.bytes.split_to(sz).freeze();
+        self.bytes.clear();
+        self.initialized -= sz;
+        chunk
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl std::ops::Deref for ReadBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl std::ops::DerefMut for ReadBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+/// The reading end of the channel from the reader thread to [SerialReadHalf].
+///
+/// Chunks read from the port are passed on as [bytes::Bytes], which saves
+/// copying them into an intermediate buffer.
+#[cfg(feature = "bytes")]
+struct ReaderChannel {
+    rx: tokio::sync::mpsc::Receiver<bytes::Bytes>,
+    /// The part of the last received chunk not yet read.
+    pending: bytes::Bytes,
+}
+
+#[cfg(feature = "bytes")]
+impl tokio::io::AsyncRead for ReaderChannel {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        if self.pending.is_empty() {
+            match std::task::ready!(self.rx.poll_recv(cx)) {
+                Some(chunk) => self.pending = chunk,
+                // The reader thread exited.
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+        let sz = self.pending.len().min(buf.remaining());
+        buf.put_slice(&self.pending.split_to(sz));
+        Poll::Ready(Ok(()))
     }
 }
 
 /// Write loop, launched on own thread. Returns only on error.
+///
+/// Commands are handled as they arrive. Before handling one, except for
+/// [ControlCommand::Stop], all bytes already queued in `rx` are written, so that
+/// commands are ordered after every write which completed before the command
+/// was sent.
 fn writer(
     mut port: Box<dyn SerialPort>,
-    rx: tokio::io::ReadHalf<tokio::io::DuplexStream>,
-) -> Result<NeverOk, Error> {
-    let mut rx = tokio_util::io::ReaderStream::new(rx);
-    while let Some(buf) = futures::executor::block_on(rx.next()) {
-        let buf = buf?;
-        port.write_all(&buf[..])?
+    rx: Arc<WriteQueue>,
+    mut commands: tokio::sync::mpsc::UnboundedReceiver<ControlCommand>,
+    write_timeout: Option<std::time::Duration>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+    rs485: Option<crate::Rs485Config>,
+    chunks: WriteChunks,
+) -> Result<NeverOk, SerialError> {
+    let mut buf = vec![0u8; WRITE_CHUNK_SIZE];
+    loop {
+        if cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+            return Err(SerialError::Cancelled);
+        }
+        let next = futures::executor::block_on(async {
+            let read = futures::future::poll_fn(|cx| rx.poll_take(cx, &mut buf));
+            let command = commands.recv();
+            let cancelled = async {
+                match &cancel {
+                    Some(cancel) => cancel.cancelled().await,
+                    None => std::future::pending().await,
+                }
+            };
+            futures::pin_mut!(read, command, cancelled);
+            match futures::future::select(futures::future::select(read, command), cancelled).await {
+                Either::Left((Either::Left((sz, _)), _)) => Some(Either::Left(sz)),
+                Either::Left((Either::Right((command, _)), _)) => Some(Either::Right(command)),
+                Either::Right(_) => None,
+            }
+        });
+        let Some(next) = next else {
+            return Err(SerialError::Cancelled);
+        };
+        match next {
+            Either::Left(sz) => match sz? {
+                0 => return Err(SerialError::ChannelClosed),
+                sz => match &rs485 {
+                    None => write_port(port.as_mut(), &buf[..sz], &chunks)?,
+                    Some(rs485) => transmit_rs485(
+                        port.as_mut(),
+                        rs485,
+                        &rx,
+                        &mut buf,
+                        sz,
+                        write_timeout,
+                        &chunks,
+                    )?,
+                },
+            },
+            Either::Right(Some(command)) => match command {
+                ControlCommand::Flush(done) => {
+                    write_queued(port.as_mut(), &rx, &mut buf, &chunks)?;
+                    let _ = done.send(flush(port.as_mut(), write_timeout));
+                }
+                ControlCommand::HandOff(done) => {
+                    write_queued(port.as_mut(), &rx, &mut buf, &chunks)?;
+                    let _ = done.send(Ok(()));
+                }
+                ControlCommand::Break(duration, done) => {
+                    write_queued(port.as_mut(), &rx, &mut buf, &chunks)?;
+                    let _ = done.send(send_break(port.as_mut(), duration, write_timeout));
+                }
+                ControlCommand::Pause(done, resume) => {
+                    write_queued(port.as_mut(), &rx, &mut buf, &chunks)?;
+                    if done.send(flush(port.as_mut(), write_timeout)).is_ok() {
+                        let _ = futures::executor::block_on(resume);
+                    }
+                }
+                ControlCommand::Stop => return Err(SerialError::Closed),
+            },
+            // The stream was dropped.
+            Either::Right(None) => {
+                write_queued(port.as_mut(), &rx, &mut buf, &chunks)?;
+                return Err(SerialError::ChannelClosed);
+            }
+        }
+    }
+}
+
+/// Transmit the first `sz` bytes of `buf` and anything queued in `rx` meanwhile
+/// with RTS asserted.
+fn transmit_rs485(
+    port: &mut dyn SerialPort,
+    rs485: &crate::Rs485Config,
+    rx: &WriteQueue,
+    buf: &mut [u8],
+    sz: usize,
+    write_timeout: Option<std::time::Duration>,
+    chunks: &WriteChunks,
+) -> Result<(), SerialError> {
+    port.write_request_to_send(!rs485.rts_low_when_sending)
+        .map_err(|e| port_error(e.into()))?;
+    std::thread::sleep(rs485.delay_before_send);
+    let res = write_port(port, &buf[..sz], chunks)
+        .and_then(|()| write_queued(port, rx, buf, chunks))
+        .and_then(|()| flush(port, write_timeout).map_err(port_error));
+    // Release the bus even if transmitting failed.
+    if res.is_ok() {
+        std::thread::sleep(rs485.delay_after_send);
+    }
+    let released = port.write_request_to_send(rs485.rts_low_when_sending);
+    res?;
+    released.map_err(|e| port_error(e.into()))
+}
+
+/// Wait until everything written to `port` so far was transmitted.
+///
+/// With a `write_timeout`, the output queue of the port is polled until it is
+/// empty so that waiting can be given up.
+fn flush(
+    port: &mut dyn SerialPort,
+    write_timeout: Option<std::time::Duration>,
+) -> std::io::Result<()> {
+    if let Some(write_timeout) = write_timeout {
+        let start = std::time::Instant::now();
+        while port.bytes_to_write()? > 0 {
+            if start.elapsed() >= write_timeout {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "port did not transmit within the write timeout",
+                ));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+    port.flush()
+}
+
+/// Transmit everything written to `port` so far, then hold a break condition
+/// for `duration`.
+fn send_break(
+    port: &mut dyn SerialPort,
+    duration: std::time::Duration,
+    write_timeout: Option<std::time::Duration>,
+) -> std::io::Result<()> {
+    flush(port, write_timeout)?;
+    port.set_break()?;
+    std::thread::sleep(duration);
+    port.clear_break()?;
+    Ok(())
+}
+
+/// Write the bytes currently queued in `rx` to `port` without waiting for more.
+fn write_queued(
+    port: &mut dyn SerialPort,
+    rx: &WriteQueue,
+    buf: &mut [u8],
+    chunks: &WriteChunks,
+) -> Result<(), SerialError> {
+    while let Some(sz) = futures::future::poll_fn(|cx| rx.poll_take(cx, buf)).now_or_never() {
+        match sz? {
+            0 => break,
+            sz => write_port(port, &buf[..sz], chunks)?,
+        }
+    }
+    Ok(())
+}
+
+/// Hand all of `buf` to `port` in `chunks`, counting the bytes.
+fn write_port(
+    port: &mut dyn SerialPort,
+    buf: &[u8],
+    chunks: &WriteChunks,
+) -> Result<(), SerialError> {
+    for (i, chunk) in buf.chunks(chunks.size).enumerate() {
+        if i > 0 && !chunks.pause.is_zero() {
+            std::thread::sleep(chunks.pause);
+        }
+        write_all(port, chunk).map_err(port_error)?;
+        trace(&chunks.tracer, Direction::Write, chunk);
+        chunks
+            .counters
+            .count_written(chunk.len(), std::time::Instant::now());
+        if let Some(inflight) = &chunks.inflight {
+            inflight.add_permits(chunk.len());
+        }
+    }
+    Ok(())
+}
+
+/// Write all of `buf` to `port`, like [std::io::Write::write_all].
+///
+/// With [SerialPortBuilder::nonblocking_io], the port may have no room for a
+/// moment after reporting that it has, e.g. when a clone wrote first, and is
+/// written to again.
+fn write_all(port: &mut dyn SerialPort, mut buf: &[u8]) -> std::io::Result<()> {
+    while !buf.is_empty() {
+        match port.write(buf) {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(sz) => buf = &buf[sz..],
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::Interrupted | std::io::ErrorKind::WouldBlock
+                ) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Check that `port` runs at about the `requested` baud rate.
+///
+/// Drivers may silently substitute a rate they support. Ports which cannot
+/// report their rate are not checked, nor is a requested rate of zero, which
+/// leaves the rate unchanged.
+fn check_baud_rate(port: &dyn SerialPort, requested: u32) -> std::io::Result<()> {
+    if requested == 0 {
+        return Ok(());
+    }
+    let Ok(actual) = port.baud_rate() else {
+        return Ok(());
+    };
+    if u64::from(actual.abs_diff(requested)) * 100
+        > u64::from(requested) * BAUD_RATE_TOLERANCE_PERCENT
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "port {} does not support a baud rate of {requested}, it runs at {actual}",
+                port.name().unwrap_or_default()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Set `baud_rate` on the terminal `fd` refers to with `BOTHER`, which takes
+/// any rate rather than one of the standard ones.
+#[cfg(target_os = "linux")]
+fn set_custom_baud_rate(fd: std::os::unix::io::RawFd, baud_rate: u32) -> std::io::Result<()> {
+    // SAFETY: `termios2` is plain data which `TCGETS2` fills in.
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios2>() };
+    // SAFETY: `fd` is an open descriptor and `termios` is valid for writes.
+    if unsafe { libc::ioctl(fd, libc::TCGETS2, &mut termios) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    termios.c_cflag &= !(libc::CBAUD | libc::CIBAUD);
+    termios.c_cflag |= libc::BOTHER;
+    termios.c_ispeed = baud_rate;
+    termios.c_ospeed = baud_rate;
+    // SAFETY: As above, and `termios` was filled in by `TCGETS2`.
+    if unsafe { libc::ioctl(fd, libc::TCSETS2, &termios) } == -1 {
+        return Err(std::io::Error::last_os_error());
     }
-    Err(Error::SenderClosed)
+    Ok(())
 }
 
 /// Opens a serial port and returns a [SerialStream] to read and write to
@@ -171,60 +3715,289 @@ fn writer(
 ///
 /// Reading and writing to the serial port is handled by two newly spawned
 /// threads.
+/// `port_fd` is a descriptor of the port, if known, which the reader thread
+/// waits on with [SerialPortBuilder::nonblocking_io].
 fn open(
     mut port: Box<dyn serialport::SerialPort>,
-    max_buf_size: usize,
+    options: StreamOptions,
+    port_fd: Option<std::os::unix::io::RawFd>,
 ) -> std::io::Result<SerialStream> {
+    let read_buf_size = options.read_buf_size();
+    let write_buf_size = options.write_buf_size();
+    let StreamOptions {
+        timeout,
+        read_idle_timeout,
+        write_timeout,
+        write_policy,
+        max_inflight_write,
+        unbounded_write_buffer,
+        empty_read_policy,
+        nonblocking_io,
+        read_chunk_size,
+        read_coalesce,
+        write_chunk_size,
+        write_chunk_pause,
+        report_input_errors,
+        disconnect_as_eof,
+        direction,
+        loopback,
+        cancel,
+        rs485,
+        counters,
+        spawner,
+        tracer,
+        ..
+    } = options.clone();
+    options.validate()?;
+    let inflight = max_inflight_write.map(|max| {
+        Arc::new(tokio::sync::Semaphore::new(
+            max.min(tokio::sync::Semaphore::MAX_PERMITS),
+        ))
+    });
+
     // Convert port to blocking (more-or-less). Actually a 100 year timeout.
     // See https://github.com/serialport/serialport-rs/pull/185 for full blocking.
-    port.set_timeout(std::time::Duration::from_secs(60 * 60 * 24 * 365 * 100))?;
+    // The reader thread sets its own, shorter, timeout on its handle.
+    port.set_timeout(FOREVER)?;
+    let name = port.name();
 
-    let write_port = port.try_clone()?;
+    let (read_port, mut write_port) = if loopback {
+        let port = crate::memory::MemoryPort::looped();
+        let read_port = port.try_clone()?;
+        (Some(read_port), Some(Box::new(port) as Box<dyn SerialPort>))
+    } else {
+        let first = match port.try_clone() {
+            Ok(clone) => clone,
+            // The threads then take turns on the one handle.
+            Err(_) => {
+                port = Box::new(crate::shared::SharedPort::new(port));
+                port.try_clone()?
+            }
+        };
+        match direction {
+            crate::StreamDirection::ReadWrite => (Some(first), Some(port.try_clone()?)),
+            crate::StreamDirection::ReadOnly => (Some(first), None),
+            crate::StreamDirection::WriteOnly => (None, Some(first)),
+        }
+    };
+    if let Some(write_port) = &mut write_port {
+        if let Some(write_timeout) = write_timeout {
+            write_port.set_timeout(write_timeout)?;
+        }
+        if let Some(rs485) = rs485 {
+            write_port.write_request_to_send(rs485.rts_low_when_sending)?;
+        }
+    }
 
-    let (for_rw_threads, duplex) = tokio::io::duplex(max_buf_size);
-    let (read_half, write_half) = tokio::io::split(for_rw_threads);
-    let (read_thread_result_tx, read_thread_result_rx) = tokio::sync::oneshot::channel();
-    std::thread::spawn(move || {
-        read_thread_result_tx
-            .send(reader(port, write_half))
-            .unwrap();
+    // Created before the reader thread is spawned, so that the thread stops
+    // if spawning the writer thread fails.
+    let stop_reader = SetOnDrop(Arc::new(AtomicBool::new(false)));
+    // In loopback mode, the reader thread does not read from the port.
+    let (ready, wake_reader) = match port_fd {
+        Some(port_fd) if nonblocking_io && !loopback => {
+            // SAFETY: `port_fd` belongs to a handle of the port which is open
+            // during this call.
+            let port =
+                unsafe { std::os::fd::BorrowedFd::borrow_raw(port_fd) }.try_clone_to_owned()?;
+            set_nonblocking(port_fd)?;
+            let (wake, woken) = std::os::unix::net::UnixStream::pair()?;
+            (Some(ReadyPoll { port, woken }), Some(wake))
+        }
+        _ => (None, None),
+    };
+    let reader_stop = ReaderStop {
+        closed: stop_reader.0.clone(),
+        cancel: cancel.clone(),
+        ready,
+    };
+    let idle = Arc::new(IdleSignal {
+        timeout: read_idle_timeout,
+        ..Default::default()
     });
+    let reader_idle = idle.clone();
+    let timeout = Arc::new(std::sync::Mutex::new(timeout));
+    let reader_timeout = timeout.clone();
+    let counters = counters.unwrap_or_default();
+    let reader_counters = counters.clone();
+    let options = StreamOptions {
+        counters: Some(counters.clone()),
+        ..options
+    };
+    // Each direction gets its own channel so that dropping one half of the
+    // stream is noticed by the corresponding thread. Only one direction of each
+    // duplex is used.
+    let (coalesce_delay, coalesce_size) = read_coalesce.unwrap_or_default();
+    let chunks = ReadChunks {
+        size: read_chunk_size.max(1),
+        coalesce_delay,
+        coalesce_size: coalesce_size.max(1),
+        // In loopback mode, the bytes read do not come from the port.
+        marked_errors: report_input_errors && !loopback,
+        empty_read_policy,
+        tracer: tracer.clone(),
+    };
+    let write_chunks = WriteChunks {
+        size: write_chunk_size.max(1),
+        pause: write_chunk_pause,
+        inflight: inflight.clone(),
+        counters,
+        tracer,
+    };
+    let (read_thread_channel, reader_channel) = reader_channel(read_buf_size, read_chunk_size);
+    let (writer_sink, write_thread_source) =
+        WriteSink::new((!unbounded_write_buffer).then_some(write_buf_size));
+    let queue = Arc::new(WriteQueue::new(write_thread_source));
+    let write_thread_queue = queue.clone();
+    let (commands, write_thread_commands) = tokio::sync::mpsc::unbounded_channel();
+    let (read_thread_result_tx, read_thread_result_rx) = tokio::sync::oneshot::channel();
+    // Sending fails only if the stream was dropped, in which case nobody is
+    // interested in the result.
+    let read_thread = match read_port {
+        Some(read_port) => Some(WorkerThread::spawn(
+            Direction::Read,
+            name.as_deref(),
+            spawner.as_ref(),
+            move || {
+                let _ = read_thread_result_tx.send(catch_panic(|| {
+                    reader(
+                        read_port,
+                        read_thread_channel,
+                        reader_stop,
+                        reader_timeout,
+                        reader_idle,
+                        chunks,
+                        reader_counters,
+                    )
+                }));
+            },
+        )?),
+        None => {
+            let _ = read_thread_result_tx.send(Err(SerialError::Disabled));
+            None
+        }
+    };
     let (write_thread_result_tx, write_thread_result_rx) = tokio::sync::oneshot::channel();
-    std::thread::spawn(move || {
-        write_thread_result_tx
-            .send(writer(write_port, read_half))
-            .unwrap();
-    });
+    let write_thread = match write_port {
+        Some(write_port) => Some(WorkerThread::spawn(
+            Direction::Write,
+            name.as_deref(),
+            spawner.as_ref(),
+            move || {
+                let _ = write_thread_result_tx.send(catch_panic(|| {
+                    writer(
+                        write_port,
+                        write_thread_queue,
+                        write_thread_commands,
+                        write_timeout,
+                        cancel,
+                        rs485,
+                        write_chunks,
+                    )
+                }));
+            },
+        )?),
+        None => {
+            let _ = write_thread_result_tx.send(Err(SerialError::Disabled));
+            None
+        }
+    };
 
-    let (reader_duplex, writer_duplex) = tokio::io::split(duplex);
     Ok(SerialStream {
-        read_err: Box::pin(read_thread_result_rx.map(flatten)),
-        write_err: Box::pin(write_thread_result_rx.map(flatten)),
-        reader_duplex,
-        writer_duplex,
+        reader: SerialReadHalf {
+            read_err: WorkerResult::new({
+                let name = name.clone();
+                read_thread_result_rx.map(|res| flatten(Direction::Read, name, res))
+            }),
+            reader_channel,
+            idle,
+            timeout,
+            stop_reader,
+            wake_reader,
+            thread: read_thread,
+            cleared: AtomicBool::new(false),
+            peeked: Vec::new(),
+            disconnect_as_eof,
+        },
+        writer: SerialWriteHalf {
+            write_err: WorkerResult::new({
+                let name = name.clone();
+                write_thread_result_rx.map(|res| flatten(Direction::Write, name, res))
+            }),
+            writer_sink,
+            queue,
+            write_policy,
+            inflight: inflight.map(tokio_util::sync::PollSemaphore::new),
+            commands,
+            flush_done: None,
+            thread: write_thread,
+            name: name.clone(),
+        },
+        control: std::sync::Mutex::new(Some(port)),
+        name,
+        raw_fd: None,
+        fd: None,
+        usb_info: std::sync::OnceLock::new(),
+        options,
+        builder: None,
+    })
+}
+
+/// Run the loop of a worker thread, turning a panic into an error so that it is
+/// reported to the stream.
+fn catch_panic(
+    worker: impl FnOnce() -> Result<NeverOk, SerialError>,
+) -> Result<NeverOk, SerialError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(worker)).unwrap_or_else(|payload| {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => (*message).to_owned(),
+                Err(_) => "unknown panic payload".to_owned(),
+            },
+        };
+        Err(SerialError::Panicked(message))
     })
 }
 
+/// Whether a worker thread has been started and not exited yet.
+fn is_alive(thread: &Option<WorkerThread>) -> bool {
+    thread.as_ref().is_some_and(|thread| !thread.is_finished())
+}
+
+/// Prepare spawning a worker thread, named after its direction and the port.
+fn worker_thread(direction: Direction, port: Option<&str>) -> std::thread::Builder {
+    let name = match port {
+        Some(port) => format!("serial-{direction}:{port}"),
+        None => format!("serial-{direction}"),
+    };
+    std::thread::Builder::new().name(name)
+}
+
 /// convert our Result type to Result from std::io
-fn to_std_io<T>(res: Result<NeverOk, Error>) -> std::io::Result<T> {
+fn to_std_io<T>(res: Result<NeverOk, WorkerError>) -> std::io::Result<T> {
     match res {
         Ok(never) => assert_never!(never),
-        Err(e) => match e {
-            Error::Io(e) => Err(e),
-            other => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("{other}"),
-            )),
-        },
+        Err(e) => Err(std::io::Error::new(e.kind(), e)),
     }
 }
 
-/// flatten Result<Result<_>> to Result<_>
+/// flatten Result<Result<_>> to Result<_>, tagging errors with the port and
+/// thread they came from
 fn flatten(
-    full: Result<Result<NeverOk, Error>, tokio::sync::oneshot::error::RecvError>,
-) -> Result<NeverOk, Error> {
-    match full {
-        Ok(res) => res,
-        Err(e) => Err(Error::OneshotRecv(e)),
-    }
+    direction: Direction,
+    port: Option<String>,
+    full: Result<Result<NeverOk, SerialError>, tokio::sync::oneshot::error::RecvError>,
+) -> Result<NeverOk, WorkerError> {
+    let error = match full {
+        Ok(Ok(never)) => assert_never!(never),
+        Ok(Err(e)) => e,
+        // Panics are caught, so this happens only if they abort or if a
+        // `ThreadSpawner` dropped the body without running it.
+        Err(_) => SerialError::Panicked("thread exited without a result".into()),
+    };
+    Err(WorkerError {
+        port,
+        direction,
+        error: Arc::new(error),
+    })
 }
@@ -0,0 +1,201 @@
+//! A stream which reopens its port after the device went away.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::{SerialError, SerialPortBuilder, SerialStream, WorkerError};
+
+/// How [ReconnectingSerial] retries opening the port.
+///
+/// After a failed attempt, the delay before the next one doubles, starting at
+/// `initial_delay` and capped at `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReconnectPolicy {
+    /// How long to wait before the first attempt to reopen the port.
+    pub initial_delay: Duration,
+    /// The longest delay between attempts.
+    pub max_delay: Duration,
+    /// How many attempts in a row may fail before the error is returned, or
+    /// `None` to keep trying forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    /// Retry forever, waiting from 100 milliseconds up to 10 seconds.
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(1 << attempt.min(31))
+            .min(self.max_delay)
+    }
+}
+
+/// A stream which reopens the port after the device was disconnected.
+///
+/// This implements [tokio::io::AsyncRead] and [tokio::io::AsyncWrite] like
+/// [SerialStream]. When reading or writing fails because the device went away,
+/// e.g. when a USB adapter drops off the bus, the port is reopened from the
+/// builder following the [ReconnectPolicy], and the read or write is retried on
+/// the new port. Other errors are returned as usual.
+///
+/// Bytes received before a disconnect are read before the port is reopened.
+/// Bytes written but not transmitted yet are lost. A successful flush before
+/// the disconnect guarantees only that earlier bytes were transmitted. Framed
+/// protocols should therefore be prepared to resynchronize.
+///
+/// The old stream is dropped when the disconnect is noticed, and its worker
+/// threads may hold the port for a moment longer, see [SerialStream]. If
+/// reopening fails because of that, it is retried like any other failed
+/// attempt.
+///
+/// The port is opened on first use rather than by [ReconnectingSerial::new],
+/// so a device which is not plugged in yet is waited for in the same way. If
+/// the policy gives up, the last error is returned, and the next read or write
+/// starts over with a new round of attempts. This must be used from within a
+/// tokio runtime with the time driver enabled.
+pub struct ReconnectingSerial {
+    builder: SerialPortBuilder,
+    policy: ReconnectPolicy,
+    state: State,
+    /// The number of failed attempts since the port was last open.
+    attempt: u32,
+}
+
+enum State {
+    Open(Box<SerialStream>),
+    Waiting(Pin<Box<tokio::time::Sleep>>),
+    Opening(Pin<Box<dyn Future<Output = std::io::Result<SerialStream>> + Send>>),
+}
+
+impl ReconnectingSerial {
+    /// Create a stream which opens the port with `builder` on first use and
+    /// reopens it according to `policy`.
+    pub fn new(builder: SerialPortBuilder, policy: ReconnectPolicy) -> Self {
+        let state = State::Opening(Box::pin(builder.clone().open_native_async_nonblocking()));
+        Self {
+            builder,
+            policy,
+            state,
+            attempt: 0,
+        }
+    }
+
+    /// Get the currently open stream, e.g. to use its control methods.
+    ///
+    /// This is `None` while the port is being reopened.
+    pub fn get_ref(&self) -> Option<&SerialStream> {
+        match &self.state {
+            State::Open(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    /// Get the currently open stream mutably, if any.
+    pub fn get_mut(&mut self) -> Option<&mut SerialStream> {
+        match &mut self.state {
+            State::Open(stream) => Some(&mut **stream),
+            _ => None,
+        }
+    }
+
+    /// Wait until the port is open and run `f` on it, reconnecting when `f`
+    /// fails because the device went away.
+    fn poll_with<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut f: impl FnMut(Pin<&mut SerialStream>, &mut Context<'_>) -> Poll<std::io::Result<T>>,
+    ) -> Poll<std::io::Result<T>> {
+        loop {
+            match &mut self.state {
+                State::Open(stream) => match f(Pin::new(&mut **stream), cx) {
+                    Poll::Ready(Err(e)) if is_disconnect(&e) => {
+                        self.state =
+                            State::Waiting(Box::pin(tokio::time::sleep(self.policy.delay(0))));
+                    }
+                    res => return res,
+                },
+                State::Waiting(sleep) => {
+                    std::task::ready!(sleep.as_mut().poll(cx));
+                    self.state = State::Opening(Box::pin(
+                        self.builder.clone().open_native_async_nonblocking(),
+                    ));
+                }
+                State::Opening(open) => match std::task::ready!(open.as_mut().poll(cx)) {
+                    Ok(stream) => {
+                        self.attempt = 0;
+                        self.state = State::Open(Box::new(stream));
+                    }
+                    Err(e) => {
+                        self.attempt += 1;
+                        let delay = self.policy.delay(self.attempt);
+                        self.state = State::Waiting(Box::pin(tokio::time::sleep(delay)));
+                        if self
+                            .policy
+                            .max_attempts
+                            .is_some_and(|max| self.attempt >= max)
+                        {
+                            self.attempt = 0;
+                            return Poll::Ready(Err(e));
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Whether `e` was caused by the device going away.
+fn is_disconnect(e: &std::io::Error) -> bool {
+    e.get_ref()
+        .and_then(|e| e.downcast_ref::<WorkerError>())
+        .is_some_and(|e| matches!(e.error(), SerialError::Disconnected(_)))
+}
+
+impl std::fmt::Debug for ReconnectingSerial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconnectingSerial")
+            .field("builder", &self.builder)
+            .field("policy", &self.policy)
+            .field("stream", &self.get_ref())
+            .finish_non_exhaustive()
+    }
+}
+
+impl tokio::io::AsyncRead for ReconnectingSerial {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).poll_with(cx, |stream, cx| stream.poll_read(cx, buf))
+    }
+}
+
+impl tokio::io::AsyncWrite for ReconnectingSerial {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::into_inner(self).poll_with(cx, |stream, cx| stream.poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).poll_with(cx, |stream, cx| stream.poll_flush(cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::into_inner(self).poll_with(cx, |stream, cx| stream.poll_shutdown(cx))
+    }
+}
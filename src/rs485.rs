@@ -0,0 +1,19 @@
+//! Settings for half-duplex RS-485.
+
+/// How the writer thread drives RTS to switch an RS-485 transceiver between
+/// sending and receiving, set with `SerialPortBuilder::rs485`.
+///
+/// RTS is asserted before the writer thread hands bytes to the port, and
+/// released once the port has transmitted the last of them, so that the bus is
+/// free for the reply. Bytes written while a transmission is in progress are
+/// sent in the same transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rs485Config {
+    /// How long to wait after asserting RTS before transmitting.
+    pub delay_before_send: std::time::Duration,
+    /// How long to keep RTS asserted after the last byte was transmitted.
+    pub delay_after_send: std::time::Duration,
+    /// Whether RTS is driven low rather than high while transmitting, for
+    /// transceivers with an inverted driver enable.
+    pub rts_low_when_sending: bool,
+}
@@ -0,0 +1,141 @@
+//! A set of streams read together.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio_util::bytes::{Bytes, BytesMut};
+
+use crate::SerialStream;
+
+/// Several streams read as one, yielding data from whichever has some first.
+///
+/// This implements [futures::Stream] with items holding the index of a stream,
+/// in the order it was added, and the bytes read from it, which are those
+/// available at the time, up to the read buffer size of that stream. Streams
+/// which have data at the same time take turns, so a busy port does not starve
+/// the others.
+///
+/// A stream which reaches its end yields an empty chunk once. A stream which
+/// fails yields the error once, unless it is [std::io::ErrorKind::TimedOut]
+/// from a read timeout. Either way, it is not read from again, but it stays in
+/// the set with its index and can still be written to with
+/// [SerialSet::get_mut]. Once no stream is left to read from, the set ends.
+#[derive(Debug, Default)]
+pub struct SerialSet {
+    entries: Vec<Entry>,
+    /// The index of the stream polled first next time.
+    next: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    stream: SerialStream,
+    buf: BytesMut,
+    done: bool,
+}
+
+impl SerialSet {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `stream` to the set and return its index.
+    pub fn push(&mut self, stream: SerialStream) -> usize {
+        self.entries.push(Entry {
+            stream,
+            buf: BytesMut::new(),
+            done: false,
+        });
+        self.entries.len() - 1
+    }
+
+    /// The number of streams in the set, including those no longer read.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the set holds no streams.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the stream at `index`, e.g. to use its control methods.
+    pub fn get(&self, index: usize) -> Option<&SerialStream> {
+        self.entries.get(index).map(|entry| &entry.stream)
+    }
+
+    /// Get the stream at `index` mutably, e.g. to write to it.
+    ///
+    /// Bytes read from it directly are not yielded by the set.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut SerialStream> {
+        self.entries.get_mut(index).map(|entry| &mut entry.stream)
+    }
+
+    /// Wait for the next chunk of data read from any stream.
+    ///
+    /// This returns the index of the stream along with the result of reading,
+    /// or `None` once no stream is left to read from. It is a shorthand for
+    /// [futures::StreamExt::next] and is cancel safe.
+    pub async fn next_readable(&mut self) -> Option<(usize, std::io::Result<Bytes>)> {
+        futures::StreamExt::next(self).await
+    }
+
+    /// Take the streams out of the set, in the order they were added.
+    ///
+    /// Bytes read by the set but not yielded yet are discarded.
+    pub fn into_inner(self) -> Vec<SerialStream> {
+        self.entries.into_iter().map(|entry| entry.stream).collect()
+    }
+}
+
+impl FromIterator<SerialStream> for SerialSet {
+    fn from_iter<I: IntoIterator<Item = SerialStream>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for stream in iter {
+            set.push(stream);
+        }
+        set
+    }
+}
+
+impl futures::Stream for SerialSet {
+    type Item = (usize, std::io::Result<Bytes>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::into_inner(self);
+        let len = this.entries.len();
+        let mut open = false;
+        for i in (0..len).map(|k| (this.next + k) % len) {
+            let entry = &mut this.entries[i];
+            if entry.done {
+                continue;
+            }
+            open = true;
+            entry.buf.reserve(entry.stream.read_buf_size());
+            let res = match tokio_util::io::poll_read_buf(
+                Pin::new(&mut entry.stream),
+                cx,
+                &mut entry.buf,
+            ) {
+                Poll::Pending => continue,
+                Poll::Ready(Ok(0)) => {
+                    entry.done = true;
+                    Ok(Bytes::new())
+                }
+                Poll::Ready(Ok(_)) => Ok(entry.buf.split().freeze()),
+                Poll::Ready(Err(e)) => {
+                    entry.done = e.kind() != std::io::ErrorKind::TimedOut;
+                    Err(e)
+                }
+            };
+            this.next = (i + 1) % len;
+            return Poll::Ready(Some((i, res)));
+        }
+        if open {
+            Poll::Pending
+        } else {
+            Poll::Ready(None)
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! A port handle shared by all threads, for drivers which cannot clone their
+//! handle.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+/// How long a read holds the port before letting other handles use it.
+const READ_SLICE: Duration = Duration::from_millis(10);
+
+/// A handle of a port which takes turns with its clones.
+///
+/// Clones made with [SerialPort::try_clone] share the one underlying handle
+/// behind a mutex. Each operation holds the mutex while it runs, so a read
+/// blocks writes and control operations. Reads therefore wait for data in
+/// slices of [READ_SLICE], releasing the port in between, until their own
+/// timeout has passed. Writes hold the port until they complete, so with flow
+/// control stalling the output, everything else waits as well.
+pub(crate) struct SharedPort {
+    shared: Arc<Shared>,
+    timeout: Duration,
+}
+
+struct Shared {
+    port: Mutex<Box<dyn SerialPort>>,
+    /// The number of handles other than reads waiting for the port, which
+    /// reads let go first.
+    waiting: AtomicUsize,
+}
+
+impl SharedPort {
+    pub(crate) fn new(port: Box<dyn SerialPort>) -> Self {
+        let timeout = port.timeout();
+        Self {
+            shared: Arc::new(Shared {
+                port: Mutex::new(port),
+                waiting: AtomicUsize::new(0),
+            }),
+            timeout,
+        }
+    }
+
+    /// Run `f` on the port with the timeout of this handle.
+    fn with_port<T>(&self, f: impl FnOnce(&mut dyn SerialPort) -> T) -> serialport::Result<T> {
+        self.shared.waiting.fetch_add(1, Ordering::AcqRel);
+        let mut port = crate::posix::lock(&self.shared.port);
+        self.shared.waiting.fetch_sub(1, Ordering::AcqRel);
+        port.set_timeout(self.timeout)?;
+        Ok(f(port.as_mut()))
+    }
+}
+
+impl std::io::Read for SharedPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let deadline = Instant::now().checked_add(self.timeout);
+        loop {
+            while self.shared.waiting.load(Ordering::Acquire) > 0 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            let slice = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            }
+            .min(READ_SLICE);
+            let res = {
+                let mut port = crate::posix::lock(&self.shared.port);
+                port.set_timeout(slice)?;
+                port.read(buf)
+            };
+            let timed_out = matches!(&res, Err(e) if e.kind() == std::io::ErrorKind::TimedOut);
+            if !timed_out || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return res;
+            }
+        }
+    }
+}
+
+impl std::io::Write for SharedPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.with_port(|port| port.write(buf))?
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.with_port(|port| port.flush())?
+    }
+}
+
+impl SerialPort for SharedPort {
+    fn name(&self) -> Option<String> {
+        crate::posix::lock(&self.shared.port).name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.with_port(|port| port.baud_rate())?
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.with_port(|port| port.data_bits())?
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.with_port(|port| port.flow_control())?
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.with_port(|port| port.parity())?
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.with_port(|port| port.stop_bits())?
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.with_port(|port| port.set_baud_rate(baud_rate))?
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.with_port(|port| port.set_data_bits(data_bits))?
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.with_port(|port| port.set_flow_control(flow_control))?
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.with_port(|port| port.set_parity(parity))?
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.with_port(|port| port.set_stop_bits(stop_bits))?
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.with_port(|port| port.write_request_to_send(level))?
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.with_port(|port| port.write_data_terminal_ready(level))?
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.with_port(|port| port.read_clear_to_send())?
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.with_port(|port| port.read_data_set_ready())?
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.with_port(|port| port.read_ring_indicator())?
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.with_port(|port| port.read_carrier_detect())?
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.with_port(|port| port.bytes_to_read())?
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.with_port(|port| port.bytes_to_write())?
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        self.with_port(|port| port.clear(buffer_to_clear))?
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(SharedPort {
+            shared: self.shared.clone(),
+            timeout: self.timeout,
+        }))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        self.with_port(|port| port.set_break())?
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.with_port(|port| port.clear_break())?
+    }
+}
@@ -0,0 +1,523 @@
+//! Windows implementation delegating to `tokio-serial`.
+//!
+//! The types here mirror the builder API of the implementation used on other
+//! platforms so that a single codebase compiles everywhere. The stream itself
+//! is [tokio_serial::SerialStream].
+//!
+//! `tokio-serial` has no internal buffers or worker threads, so the options
+//! which only tune those are accepted and have no effect. Options which change
+//! how the stream behaves are recorded instead, and opening fails with
+//! [std::io::ErrorKind::Unsupported] if one is set, rather than silently
+//! behaving differently than on other platforms.
+
+pub use tokio_serial::{
+    ClearBuffer, DataBits, FlowControl, Parity, SerialPort, SerialPortInfo, SerialPortType,
+    SerialStream, StopBits, UsbPortInfo,
+};
+
+/// Builder to open a serial port.
+///
+/// Create this by calling [new]. Open the port by calling
+/// [SerialPortBuilderExt::open_native_async].
+#[derive(Debug, Clone)]
+pub struct SerialPortBuilder {
+    inner: tokio_serial::SerialPortBuilder,
+    max_buf_size: usize,
+    loopback: bool,
+    rs485: bool,
+    tracer: bool,
+    report_input_errors: bool,
+    direction: crate::StreamDirection,
+    read_idle_timeout: bool,
+    write_timeout: bool,
+    empty_read_policy: crate::EmptyReadPolicy,
+    write_policy: crate::WritePolicy,
+    unbounded_write_buffer: bool,
+    write_chunk_pause: bool,
+    disconnect_as_eof: bool,
+    cancellation: bool,
+    rts_on_open: Option<bool>,
+    clear_on_open: Option<ClearBuffer>,
+}
+
+/// Create a [SerialPortBuilder] from a device path and a baud rate.
+pub fn new<'a>(path: impl Into<std::borrow::Cow<'a, str>>, baud_rate: u32) -> SerialPortBuilder {
+    SerialPortBuilder {
+        inner: tokio_serial::new(path, baud_rate),
+        max_buf_size: 1024,
+        loopback: false,
+        rs485: false,
+        tracer: false,
+        report_input_errors: false,
+        direction: crate::StreamDirection::ReadWrite,
+        read_idle_timeout: false,
+        write_timeout: false,
+        empty_read_policy: crate::EmptyReadPolicy::default(),
+        write_policy: crate::WritePolicy::default(),
+        unbounded_write_buffer: false,
+        write_chunk_pause: false,
+        disconnect_as_eof: false,
+        cancellation: false,
+        rts_on_open: None,
+        clear_on_open: None,
+    }
+}
+
+/// Create a [SerialPortBuilder] for the USB serial device with the given
+/// vendor and product ID.
+///
+/// Device names like `COM3` can change when devices are plugged into
+/// different ports. This looks up the name of the device instead, with
+/// [tokio_serial::available_ports]. Enumerating ports involves blocking system
+/// calls, so avoid calling this from an async task.
+///
+/// If no device matches, this fails with [std::io::ErrorKind::NotFound]. If
+/// several devices match, e.g. two adapters of the same model, this fails with
+/// [std::io::ErrorKind::InvalidInput], as the device to use is ambiguous.
+pub fn new_by_usb(vid: u16, pid: u16, baud_rate: u32) -> std::io::Result<SerialPortBuilder> {
+    let names: Vec<String> = tokio_serial::available_ports()?
+        .into_iter()
+        .filter(|port| {
+            matches!(&port.port_type, SerialPortType::UsbPort(usb) if usb.vid == vid && usb.pid == pid)
+        })
+        .map(|port| port.port_name)
+        .collect();
+    match names.as_slice() {
+        [name] => Ok(new(name, baud_rate)),
+        [] => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no USB serial device with ID {vid:04x}:{pid:04x}"),
+        )),
+        names => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "several USB serial devices with ID {vid:04x}:{pid:04x}: {}",
+                names.join(", ")
+            ),
+        )),
+    }
+}
+
+/// List the serial ports available on the system.
+///
+/// Enumerating ports involves blocking system calls, so this runs
+/// [tokio_serial::available_ports] on tokio's blocking thread pool. It must be
+/// called from within a tokio runtime.
+pub async fn available_ports() -> std::io::Result<Vec<SerialPortInfo>> {
+    tokio::task::spawn_blocking(tokio_serial::available_ports)
+        .await
+        .map_err(std::io::Error::other)?
+        .map_err(Into::into)
+}
+
+impl SerialPortBuilder {
+    /// Set the number of bits used to represent a character sent on the line.
+    ///
+    /// Defaults to [DataBits::Eight].
+    pub fn data_bits(self, data_bits: DataBits) -> Self {
+        Self {
+            inner: self.inner.data_bits(data_bits),
+            ..self
+        }
+    }
+
+    /// Set the type of parity to use for error checking.
+    ///
+    /// Defaults to [Parity::None].
+    pub fn parity(self, parity: Parity) -> Self {
+        Self {
+            inner: self.inner.parity(parity),
+            ..self
+        }
+    }
+
+    /// Set the number of bits to use to signal the end of a character.
+    ///
+    /// Defaults to [StopBits::One].
+    pub fn stop_bits(self, stop_bits: StopBits) -> Self {
+        Self {
+            inner: self.inner.stop_bits(stop_bits),
+            ..self
+        }
+    }
+
+    /// Set the type of signalling to use for controlling data transfer.
+    ///
+    /// Defaults to [FlowControl::None].
+    pub fn flow_control(self, flow_control: FlowControl) -> Self {
+        Self {
+            inner: self.inner.flow_control(flow_control),
+            ..self
+        }
+    }
+
+    /// Set whether the port is opened with exclusive access.
+    ///
+    /// Ports are always opened with exclusive access on Windows, so this has no
+    /// effect there.
+    pub fn exclusive(self, _exclusive: bool) -> Self {
+        self
+    }
+
+    /// Set the DTR line to `level` when the port is opened.
+    ///
+    /// This is passed on to `tokio-serial`. By default, DTR is left as the
+    /// driver sets it.
+    pub fn dtr_on_open(self, level: bool) -> Self {
+        Self {
+            inner: self.inner.dtr_on_open(level),
+            ..self
+        }
+    }
+
+    /// Set the RTS line to `level` when the port is opened.
+    ///
+    /// The line is set right after the port is opened. By default, RTS is left
+    /// as the driver sets it.
+    pub fn rts_on_open(self, level: bool) -> Self {
+        Self {
+            rts_on_open: Some(level),
+            ..self
+        }
+    }
+
+    /// Discard the given buffers of the port when it is opened.
+    ///
+    /// This happens right after the control lines were set. By default,
+    /// nothing is cleared.
+    pub fn clear_on_open(self, buffer_to_clear: ClearBuffer) -> Self {
+        Self {
+            clear_on_open: Some(buffer_to_clear),
+            ..self
+        }
+    }
+
+    /// Set whether raw mode is applied again once the port is open.
+    ///
+    /// Serial ports on Windows do not translate the bytes passing through, so
+    /// this has no effect there.
+    pub fn force_raw_mode(self, _force_raw_mode: bool) -> Self {
+        self
+    }
+
+    /// Set the amount of time to wait to receive data before timing out.
+    ///
+    /// This is passed on to `tokio-serial`.
+    pub fn timeout(self, timeout: std::time::Duration) -> Self {
+        Self {
+            inner: self.inner.timeout(timeout),
+            ..self
+        }
+    }
+
+    /// Set how long a read waits for data before reporting that the line is
+    /// idle.
+    ///
+    /// This is not supported on Windows. If it is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn read_idle_timeout(self, _read_idle_timeout: std::time::Duration) -> Self {
+        Self {
+            read_idle_timeout: true,
+            ..self
+        }
+    }
+
+    /// Set how long writing to the port may stall before giving up.
+    ///
+    /// This is not supported on Windows. If it is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn write_timeout(self, _write_timeout: std::time::Duration) -> Self {
+        Self {
+            write_timeout: true,
+            ..self
+        }
+    }
+
+    /// Set the maximum buffer size in the internal buffer.
+    ///
+    /// This has no effect on Windows.
+    pub fn max_buf_size(self, max_buf_size: usize) -> Self {
+        Self {
+            max_buf_size,
+            ..self
+        }
+    }
+
+    /// Get the maximum buffer size in the internal buffer.
+    pub fn get_max_buf_size(&self) -> usize {
+        self.max_buf_size
+    }
+
+    /// Set the size of the buffer for received bytes.
+    ///
+    /// This has no effect on Windows.
+    pub fn read_buf_size(self, _read_buf_size: usize) -> Self {
+        self
+    }
+
+    /// Set the size of the buffer for written bytes.
+    ///
+    /// This has no effect on Windows.
+    pub fn write_buf_size(self, _write_buf_size: usize) -> Self {
+        self
+    }
+
+    /// Limit the number of written bytes which have not been handed to the
+    /// port yet.
+    ///
+    /// This has no effect on Windows.
+    pub fn max_inflight_write(self, _max_inflight_write: usize) -> Self {
+        self
+    }
+
+    /// Set whether the internal buffer for writing grows without limit.
+    ///
+    /// This is not supported on Windows. If it is enabled,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn unbounded_write_buffer(self, unbounded_write_buffer: bool) -> Self {
+        Self {
+            unbounded_write_buffer,
+            ..self
+        }
+    }
+
+    /// Set whether the reader thread waits for data with `poll(2)`.
+    ///
+    /// This has no effect on Windows.
+    pub fn nonblocking_io(self, _nonblocking_io: bool) -> Self {
+        self
+    }
+
+    /// Set the size of the buffer the reader thread reads from the port into.
+    ///
+    /// This has no effect on Windows.
+    pub fn read_chunk_size(self, _read_chunk_size: usize) -> Self {
+        self
+    }
+
+    /// Set what happens when reading from the port returns no bytes.
+    ///
+    /// Only the default, [crate::EmptyReadPolicy::Retry], is supported on
+    /// Windows. If another policy is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn empty_read_policy(self, empty_read_policy: crate::EmptyReadPolicy) -> Self {
+        Self {
+            empty_read_policy,
+            ..self
+        }
+    }
+
+    /// Set what happens when the internal buffer for writing is full.
+    ///
+    /// Only the default, [crate::WritePolicy::Block], is supported on Windows.
+    /// If another policy is set, [SerialPortBuilderExt::open_native_async]
+    /// fails with [std::io::ErrorKind::Unsupported].
+    pub fn write_policy(self, write_policy: crate::WritePolicy) -> Self {
+        Self {
+            write_policy,
+            ..self
+        }
+    }
+
+    /// Collect received bytes before handing them to the stream.
+    ///
+    /// This has no effect on Windows.
+    pub fn read_coalesce(self, _delay: std::time::Duration, _size: usize) -> Self {
+        self
+    }
+
+    /// Set the most bytes the writer thread hands to the port at once.
+    ///
+    /// This has no effect on Windows.
+    pub fn write_chunk_size(self, _write_chunk_size: usize) -> Self {
+        self
+    }
+
+    /// Set how long the writer thread waits between the parts of a split
+    /// write.
+    ///
+    /// This is not supported on Windows. If a pause other than zero is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn write_chunk_pause(self, write_chunk_pause: std::time::Duration) -> Self {
+        Self {
+            write_chunk_pause: !write_chunk_pause.is_zero(),
+            ..self
+        }
+    }
+
+    /// Set whether reading reports a disconnect as the end of the stream.
+    ///
+    /// `tokio-serial` reports a disconnect as an error. If this is enabled,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn disconnect_as_eof(self, disconnect_as_eof: bool) -> Self {
+        Self {
+            disconnect_as_eof,
+            ..self
+        }
+    }
+
+    /// Set whether bytes received with a parity or framing error are
+    /// reported.
+    ///
+    /// This is not supported on Windows. If it is enabled,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported], rather than silently passing on
+    /// corrupt bytes.
+    pub fn report_input_errors(self, report_input_errors: bool) -> Self {
+        Self {
+            report_input_errors,
+            ..self
+        }
+    }
+
+    /// Set whether written bytes are read back instead of being transmitted.
+    ///
+    /// Loopback mode is not supported on Windows. If it is enabled,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn loopback(self, loopback: bool) -> Self {
+        Self { loopback, ..self }
+    }
+
+    /// Set whether the stream reads, writes or both.
+    ///
+    /// `tokio-serial` always opens ports for both directions. If anything but
+    /// [crate::StreamDirection::ReadWrite] is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn direction(self, direction: crate::StreamDirection) -> Self {
+        Self { direction, ..self }
+    }
+
+    /// Switch the direction of an RS-485 transceiver with RTS.
+    ///
+    /// This is not supported on Windows. If it is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn rs485(self, _config: crate::Rs485Config) -> Self {
+        Self {
+            rs485: true,
+            ..self
+        }
+    }
+
+    /// Pass every chunk of bytes read from or written to the port to `trace`.
+    ///
+    /// This is not supported on Windows. If it is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn with_tracer<F>(self, _trace: F) -> Self
+    where
+        F: Fn(crate::Direction, &[u8]) + Send + Sync + 'static,
+    {
+        Self {
+            tracer: true,
+            ..self
+        }
+    }
+
+    /// Launch the worker threads with `spawn` instead of [std::thread::spawn].
+    ///
+    /// This has no effect on Windows.
+    pub fn with_thread_spawner<F>(self, _spawn: F) -> Self
+    where
+        F: Fn(Box<dyn FnOnce() + Send>) -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        self
+    }
+
+    /// Stop the stream when `token` is cancelled.
+    ///
+    /// This is not supported on Windows. If it is set,
+    /// [SerialPortBuilderExt::open_native_async] fails with
+    /// [std::io::ErrorKind::Unsupported].
+    pub fn with_cancellation(self, _token: tokio_util::sync::CancellationToken) -> Self {
+        Self {
+            cancellation: true,
+            ..self
+        }
+    }
+
+    /// Check the settings without opening the port.
+    ///
+    /// This fails with [std::io::ErrorKind::Unsupported] for settings which
+    /// are not supported on Windows, as [SerialPortBuilderExt::open_native_async]
+    /// would. Whether the port exists and supports the baud rate, data bits,
+    /// parity, stop bits and flow control is known only once it is opened. To
+    /// find out for sure, open the port and drop the stream.
+    pub fn validate(&self) -> std::io::Result<()> {
+        let unsupported = [
+            (self.loopback, "loopback mode is"),
+            (self.rs485, "RS-485 direction control is"),
+            (self.tracer, "tracing is"),
+            (
+                self.direction != crate::StreamDirection::ReadWrite,
+                "read-only and write-only streams are",
+            ),
+            (self.report_input_errors, "reporting input errors is"),
+            (self.read_idle_timeout, "a read idle timeout is"),
+            (self.write_timeout, "a write timeout is"),
+            (
+                self.empty_read_policy != crate::EmptyReadPolicy::default(),
+                "this empty read policy is",
+            ),
+            (
+                self.write_policy != crate::WritePolicy::default(),
+                "this write policy is",
+            ),
+            (self.unbounded_write_buffer, "an unbounded write buffer is"),
+            (self.write_chunk_pause, "pausing between written chunks is"),
+            (
+                self.disconnect_as_eof,
+                "reporting a disconnect as the end is",
+            ),
+            (self.cancellation, "cancellation is"),
+        ];
+        match unsupported.into_iter().find(|(set, _)| *set) {
+            Some((_, what)) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("{what} not supported on Windows"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Open the port like [SerialPortBuilderExt::open_native_async], but
+    /// without blocking the calling task.
+    ///
+    /// Opening a port involves blocking system calls, which can take a while
+    /// for some devices. This runs them on tokio's blocking thread pool, so it
+    /// must be called from within a tokio runtime.
+    pub async fn open_native_async_nonblocking(self) -> std::io::Result<SerialStream> {
+        tokio::task::spawn_blocking(move || self.open_native_async())
+            .await
+            .map_err(std::io::Error::other)?
+    }
+}
+
+/// Provides a convenience function for maximum compatibility with `tokio-serial`.
+pub trait SerialPortBuilderExt {
+    /// Open a serial port and return it as a [SerialStream].
+    fn open_native_async(self) -> std::io::Result<SerialStream>;
+}
+
+impl SerialPortBuilderExt for SerialPortBuilder {
+    fn open_native_async(self) -> std::io::Result<SerialStream> {
+        self.validate()?;
+        let mut stream = SerialStream::open(&self.inner)?;
+        // Best effort, like `tokio-serial` does for DTR.
+        if let Some(level) = self.rts_on_open {
+            let _ = stream.write_request_to_send(level);
+        }
+        if let Some(buffer_to_clear) = self.clear_on_open {
+            stream.clear(buffer_to_clear)?;
+        }
+        Ok(stream)
+    }
+}
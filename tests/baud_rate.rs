@@ -0,0 +1,14 @@
+//! Ports open at non-standard baud rates where the platform supports them.
+#![cfg(target_os = "linux")]
+
+use serialport::SerialPort;
+
+#[test]
+fn opens_at_the_dmx512_rate() {
+    let (_master, slave) = serialport::TTYPort::pair().unwrap();
+    let path = slave.name().unwrap();
+    drop(slave);
+    let builder = kioto_serial::new(&path, 250_000).exclusive(false);
+    let stream = kioto_serial::SerialPortBuilderExt::open_native_async(builder).unwrap();
+    assert_eq!(stream.baud_rate().unwrap(), 250_000);
+}
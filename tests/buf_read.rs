@@ -0,0 +1,34 @@
+//! Streams implement `AsyncBufRead`, which can be mixed with reads.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn lines_are_read_without_a_buf_reader() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        master.write_all(b"first\nsecond\nrest").unwrap();
+        let mut line = String::new();
+        tokio::time::timeout(PROMPT, stream.read_line(&mut line))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(line, "first\n");
+        line.clear();
+        stream.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "second\n");
+        // Bytes buffered for reading lines are returned by reads.
+        let mut buf = [0u8; 4];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"rest");
+    });
+}
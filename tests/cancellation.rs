@@ -0,0 +1,36 @@
+//! Cancelling the token of a stream stops its reads and writes.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+use common::{block_on, open_pty_with, PROMPT};
+
+#[test]
+fn cancelling_aborts_pending_reads_and_later_writes() {
+    let token = CancellationToken::new();
+    let (mut stream, _master) = open_pty_with(|builder| builder.with_cancellation(token.clone()));
+    block_on(async {
+        let mut buf = [0u8; 8];
+        let (res, ()) =
+            futures::join!(tokio::time::timeout(PROMPT, stream.read(&mut buf)), async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                token.cancel();
+            },);
+        let e = res.expect("reading was not stopped").unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::ConnectionAborted);
+
+        let e = tokio::time::timeout(PROMPT, async {
+            stream.write_all(b"late").await?;
+            stream.flush().await
+        })
+        .await
+        .expect("writing was blocked")
+        .unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::ConnectionAborted);
+    });
+}
@@ -0,0 +1,28 @@
+//! Clearing the input discards bytes the reader thread already received.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty};
+
+#[test]
+fn clearing_the_input_discards_buffered_bytes() {
+    let (mut stream, mut master) = open_pty();
+    master.write_all(b"stale").unwrap();
+    // Give the reader thread time to move the bytes into the internal buffer.
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    stream.clear(serialport::ClearBuffer::Input).unwrap();
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        master.write_all(b"fresh").unwrap();
+        master
+    });
+    let mut buf = [0u8; 5];
+    block_on(stream.read_exact(&mut buf)).unwrap();
+    assert_eq!(&buf, b"fresh");
+    drop(writer.join().unwrap());
+}
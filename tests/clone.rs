@@ -0,0 +1,36 @@
+//! Clones of a stream must be usable on their own.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn clone_has_its_own_descriptor() {
+    let (stream, mut master) = open_pty();
+    let mut clone = stream.try_clone().unwrap();
+    let (Some(fd), Some(clone_fd)) = (stream.raw_fd(), clone.raw_fd()) else {
+        panic!("a descriptor is missing");
+    };
+    assert_ne!(fd, clone_fd);
+
+    drop(stream);
+    // The reader thread of the dropped stream notices within its poll
+    // interval that it has to stop, and could take the bytes meanwhile.
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    clone.set_inter_byte_timeout(1, 0).unwrap();
+    clone.set_exclusive(false).unwrap();
+    block_on(async {
+        master.write_all(b"ping").unwrap();
+        let mut buf = [0u8; 4];
+        tokio::time::timeout(PROMPT, clone.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"ping");
+    });
+}
@@ -0,0 +1,65 @@
+//! Closing a stream reports whether it ended cleanly.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use common::{block_on, open_pty, open_pty_with, read_exact, PROMPT};
+
+#[test]
+fn close_succeeds_after_transmitting() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        stream.write_all(b"goodbye").await.unwrap();
+        stream.flush().await.unwrap();
+        // Once the stream is closed, the other end only reports the hangup.
+        assert_eq!(read_exact(&mut master, 7), b"goodbye");
+        tokio::time::timeout(PROMPT, stream.close())
+            .await
+            .expect("closing stalled")
+            .unwrap();
+    });
+}
+
+#[test]
+fn close_reports_a_disconnect() {
+    let (stream, master) = open_pty();
+    block_on(async {
+        drop(master);
+        // Let the reader thread notice the hangup.
+        tokio::time::timeout(PROMPT, async {
+            while stream.worker_status().reader_alive {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("the hangup went unnoticed");
+        let err = tokio::time::timeout(PROMPT, stream.close())
+            .await
+            .expect("closing stalled")
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    });
+}
+
+#[test]
+fn close_reports_a_write_timeout() {
+    let (mut stream, _master) =
+        open_pty_with(|builder| builder.write_timeout(Duration::from_millis(100)));
+    block_on(async {
+        // Nothing drains the other end, so the pty fills up and writing stalls.
+        let message = vec![0u8; 1 << 20];
+        let written = tokio::time::timeout(PROMPT, stream.write_all(&message))
+            .await
+            .expect("the write timeout did not end writing");
+        assert_eq!(written.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        let err = tokio::time::timeout(PROMPT, stream.close())
+            .await
+            .expect("closing stalled")
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    });
+}
@@ -0,0 +1,131 @@
+//! Packets survive COBS framing, and bad frames do not affect the next ones.
+#![cfg(all(feature = "cobs", not(target_os = "windows")))]
+
+use kioto_serial::CobsCodec;
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+fn encode(codec: &mut CobsCodec, packet: &[u8]) -> BytesMut {
+    let mut buf = BytesMut::new();
+    codec.encode(packet, &mut buf).unwrap();
+    buf
+}
+
+/// Decode all complete frames in `buf`.
+fn decode_all(codec: &mut CobsCodec, buf: &mut BytesMut) -> Vec<std::io::Result<Vec<u8>>> {
+    let mut packets = Vec::new();
+    loop {
+        match codec.decode(buf) {
+            Ok(Some(packet)) => packets.push(Ok(packet)),
+            Ok(None) => return packets,
+            Err(e) => packets.push(Err(e)),
+        }
+    }
+}
+
+fn assert_invalid_data(res: &std::io::Result<Vec<u8>>) {
+    match res {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidData),
+        Ok(packet) => panic!("decoded {} bytes instead of failing", packet.len()),
+    }
+}
+
+#[test]
+fn packets_round_trip() {
+    let mut codec = CobsCodec::new();
+    for len in [0, 1, 254, 255, 508] {
+        for packet in [
+            (1..=255).cycle().take(len).collect::<Vec<u8>>(),
+            vec![0; len],
+            (0..=255).cycle().take(len).collect(),
+        ] {
+            let mut buf = encode(&mut codec, &packet);
+            // The delimiter is the only zero.
+            assert_eq!(buf.iter().position(|b| *b == 0), Some(buf.len() - 1));
+            assert_eq!(codec.decode(&mut buf).unwrap(), Some(packet));
+            assert!(buf.is_empty());
+        }
+    }
+}
+
+#[test]
+fn encoding_has_the_expected_overhead() {
+    let mut codec = CobsCodec::new();
+    assert_eq!(&encode(&mut codec, &[])[..], [1, 0]);
+    assert_eq!(&encode(&mut codec, &[0])[..], [1, 1, 0]);
+    assert_eq!(
+        &encode(&mut codec, &[0x11, 0, 0x22])[..],
+        [2, 0x11, 2, 0x22, 0]
+    );
+    let packet = vec![7u8; 254];
+    assert_eq!(encode(&mut codec, &packet).len(), 254 + 2);
+    let packet = vec![7u8; 255];
+    assert_eq!(encode(&mut codec, &packet).len(), 255 + 3);
+}
+
+#[test]
+fn frames_split_across_reads_are_decoded() {
+    let mut codec = CobsCodec::new();
+    let packet: Vec<u8> = (0..=255).cycle().take(508).collect();
+    let encoded = encode(&mut codec, &packet);
+    let mut buf = BytesMut::new();
+    for piece in encoded.chunks(100) {
+        assert!(buf.is_empty() || codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(piece);
+    }
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(packet));
+}
+
+#[test]
+fn empty_frames_are_skipped() {
+    let mut codec = CobsCodec::new();
+    let mut buf = BytesMut::from(&[0, 0, 2, 9, 0, 0][..]);
+    let packets = decode_all(&mut codec, &mut buf);
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].as_ref().unwrap(), &[9]);
+}
+
+#[test]
+fn decoding_resyncs_after_a_corrupt_frame() {
+    let mut codec = CobsCodec::new();
+    // The code claims more bytes than the frame holds.
+    let mut buf = BytesMut::from(&[5, 1, 2, 0][..]);
+    buf.extend_from_slice(&encode(&mut codec, b"next"));
+    let packets = decode_all(&mut codec, &mut buf);
+    assert_eq!(packets.len(), 2);
+    assert_invalid_data(&packets[0]);
+    assert_eq!(packets[1].as_ref().unwrap(), b"next");
+}
+
+#[test]
+fn over_long_frames_are_rejected() {
+    let mut long = CobsCodec::new();
+    let mut codec = CobsCodec::new_with_max_length(4);
+    let mut buf = encode(&mut long, b"too long");
+    buf.extend_from_slice(&encode(&mut long, b"fits"));
+    let packets = decode_all(&mut codec, &mut buf);
+    assert_eq!(packets.len(), 2);
+    assert_invalid_data(&packets[0]);
+    assert_eq!(packets[1].as_ref().unwrap(), b"fits");
+
+    let err = codec.encode(b"too long", &mut BytesMut::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn over_long_frames_without_delimiter_are_skipped() {
+    let mut codec = CobsCodec::new_with_max_length(4);
+    // No delimiter arrives before the frame exceeds the maximum length, so
+    // this fails once, and the rest up to the next delimiter is discarded.
+    let mut buf = BytesMut::from(&[9u8; 20][..]);
+    let packets = decode_all(&mut codec, &mut buf);
+    assert_eq!(packets.len(), 1);
+    assert_invalid_data(&packets[0]);
+    buf.extend_from_slice(&[9u8; 20]);
+    assert!(decode_all(&mut codec, &mut buf).is_empty());
+    buf.extend_from_slice(&[9, 0]);
+    buf.extend_from_slice(&encode(&mut codec, b"ok"));
+    let packets = decode_all(&mut codec, &mut buf);
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].as_ref().unwrap(), b"ok");
+}
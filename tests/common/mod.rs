@@ -0,0 +1,43 @@
+//! Helpers for tests on pseudo-terminals.
+#![allow(dead_code)]
+
+use std::io::Read;
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+/// How long an operation which should complete promptly may take.
+pub const PROMPT: Duration = Duration::from_secs(2);
+
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Open a stream on one end of a pseudo-terminal and return it with the other
+/// end.
+pub fn open_pty() -> (kioto_serial::SerialStream, serialport::TTYPort) {
+    open_pty_with(|builder| builder)
+}
+
+/// Like [open_pty], changing the settings with `configure`.
+pub fn open_pty_with(
+    configure: impl FnOnce(kioto_serial::SerialPortBuilder) -> kioto_serial::SerialPortBuilder,
+) -> (kioto_serial::SerialStream, serialport::TTYPort) {
+    let (mut master, slave) = serialport::TTYPort::pair().unwrap();
+    master.set_timeout(PROMPT).unwrap();
+    let path = slave.name().unwrap();
+    drop(slave);
+    let builder = configure(kioto_serial::new(&path, 115_200).exclusive(false));
+    let stream = kioto_serial::SerialPortBuilderExt::open_native_async(builder).unwrap();
+    (stream, master)
+}
+
+pub fn read_exact(port: &mut serialport::TTYPort, len: usize) -> Vec<u8> {
+    let mut buf = vec![0; len];
+    port.read_exact(&mut buf).unwrap();
+    buf
+}
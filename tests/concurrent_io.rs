@@ -0,0 +1,73 @@
+//! Reads pending on the port must not hold up writes.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, open_pty, read_exact, PROMPT};
+
+#[test]
+fn write_while_read_is_pending() {
+    let (stream, mut master) = open_pty();
+    block_on(async {
+        let (mut reader, mut writer) = stream.into_split();
+        let read = tokio::spawn(async move {
+            let mut buf = [0u8; 16];
+            let sz = reader.read(&mut buf).await.unwrap();
+            buf[..sz].to_vec()
+        });
+        // Let the read start waiting for data.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!read.is_finished());
+
+        let message: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let written = tokio::time::timeout(PROMPT, async {
+            writer.write_all(&message).await?;
+            writer.flush().await
+        });
+        // The pty has to be drained for the flush to complete.
+        let expected = message.clone();
+        let received = std::thread::spawn(move || {
+            let received = read_exact(&mut master, expected.len());
+            (master, received == expected)
+        });
+        written.await.expect("writing was blocked").unwrap();
+        let (mut master, intact) = received.join().unwrap();
+        assert!(intact);
+        assert!(!read.is_finished());
+
+        master.write_all(b"reply").unwrap();
+        let reply = tokio::time::timeout(PROMPT, read).await.unwrap().unwrap();
+        assert_eq!(reply, b"reply");
+    });
+}
+
+#[test]
+fn read_and_write_concurrently_on_one_stream() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        let responder = std::thread::spawn(move || {
+            for i in 0..100u8 {
+                assert_eq!(read_exact(&mut master, 1), [i]);
+                master.write_all(&[i.wrapping_add(1)]).unwrap();
+            }
+            // Closing the master hangs up the stream.
+            master
+        });
+        tokio::time::timeout(PROMPT * 5, async {
+            let mut buf = [0u8];
+            for i in 0..100u8 {
+                stream.write_all(&[i]).await.unwrap();
+                stream.read_exact(&mut buf).await.unwrap();
+                assert_eq!(buf, [i.wrapping_add(1)]);
+            }
+        })
+        .await
+        .expect("the exchange stalled");
+        drop(responder.join().unwrap());
+    });
+}
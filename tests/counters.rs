@@ -0,0 +1,38 @@
+//! Bytes received and transmitted are counted per port.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, open_pty, read_exact, PROMPT};
+
+#[test]
+fn bytes_are_counted_across_clones() {
+    let (mut stream, mut master) = open_pty();
+    let counters = stream.counters();
+    block_on(async {
+        master.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(stream.bytes_read(), 5);
+
+        stream.write_all(b"abc").await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(read_exact(&mut master, 3), b"abc");
+        assert_eq!(stream.bytes_written(), 3);
+
+        let mut clone = stream.try_clone().unwrap();
+        clone.write_all(b"de").await.unwrap();
+        clone.flush().await.unwrap();
+        assert_eq!(read_exact(&mut master, 2), b"de");
+    });
+    assert_eq!(stream.bytes_written(), 5);
+    assert_eq!(counters.bytes_read(), 5);
+    assert_eq!(counters.bytes_written(), 5);
+}
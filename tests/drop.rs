@@ -0,0 +1,53 @@
+//! Dropping a stream stops its worker threads quietly.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serialport::SerialPort;
+
+/// Panics anywhere in the test binary, including worker threads.
+static PANICS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn drop_right_after_open_while_data_arrives() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        PANICS.fetch_add(1, Ordering::SeqCst);
+        default_hook(info);
+    }));
+
+    let (mut master, slave) = serialport::TTYPort::pair().unwrap();
+    master
+        .set_timeout(std::time::Duration::from_millis(10))
+        .unwrap();
+    let path = slave.name().unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    let feeder = std::thread::spawn({
+        let stop = stop.clone();
+        move || {
+            while !stop.load(Ordering::Relaxed) {
+                // The pty fills up while no stream reads it.
+                let _ = master.write(&[0x55; 256]);
+            }
+        }
+    });
+    common::block_on(async {
+        for _ in 0..200 {
+            let stream = kioto_serial::SerialPortBuilderExt::open_native_async(
+                kioto_serial::new(&path, 115_200).exclusive(false),
+            )
+            .unwrap();
+            drop(stream);
+        }
+        // Let the worker threads of the last streams finish.
+        tokio::time::sleep(common::PROMPT / 4).await;
+    });
+    stop.store(true, Ordering::Relaxed);
+    drop(slave);
+    feeder.join().unwrap();
+    assert_eq!(PANICS.load(Ordering::SeqCst), 0);
+}
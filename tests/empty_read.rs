@@ -0,0 +1,62 @@
+//! Reads from the port which return no bytes retry or end the stream.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use kioto_serial::EmptyReadPolicy;
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty_with, PROMPT};
+
+/// The end-of-file character, which a terminal in canonical mode turns into a
+/// read returning no bytes.
+const EOF: u8 = 0x04;
+
+/// Open a stream in canonical mode with `empty_read_policy`.
+fn open_canonical(
+    empty_read_policy: EmptyReadPolicy,
+) -> (kioto_serial::SerialStream, serialport::TTYPort) {
+    let (stream, master) = open_pty_with(|builder| builder.empty_read_policy(empty_read_policy));
+    let fd = stream.raw_fd().unwrap();
+    // SAFETY: `termios` is valid for writing and reading, and `fd` is open.
+    unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        assert_eq!(libc::tcgetattr(fd, &mut termios), 0);
+        termios.c_lflag |= libc::ICANON;
+        assert_eq!(libc::tcsetattr(fd, libc::TCSANOW, &termios), 0);
+    }
+    (stream, master)
+}
+
+#[test]
+fn eof_ends_the_stream() {
+    let (mut stream, mut master) = open_canonical(EmptyReadPolicy::Eof);
+    block_on(async {
+        master.write_all(b"line\n").unwrap();
+        master.write_all(&[EOF]).unwrap();
+        let mut received = Vec::new();
+        tokio::time::timeout(PROMPT, stream.read_to_end(&mut received))
+            .await
+            .expect("the stream did not end")
+            .unwrap();
+        assert_eq!(received, b"line\n");
+    });
+}
+
+#[test]
+fn retry_keeps_reading() {
+    let (mut stream, mut master) = open_canonical(EmptyReadPolicy::Retry);
+    block_on(async {
+        master.write_all(b"line\n").unwrap();
+        master.write_all(&[EOF]).unwrap();
+        master.write_all(b"more\n").unwrap();
+        let mut buf = [0u8; 10];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"line\nmore\n");
+    });
+}
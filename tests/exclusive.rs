@@ -0,0 +1,51 @@
+//! Exclusive access keeps others from opening the port.
+#![cfg(not(target_os = "windows"))]
+
+use serialport::SerialPort;
+
+/// Create a pseudo-terminal and return it with the path of the end to open.
+fn pty() -> (serialport::TTYPort, String) {
+    let (master, slave) = serialport::TTYPort::pair().unwrap();
+    let path = slave.name().unwrap();
+    (master, path)
+}
+
+fn open(path: &str, exclusive: bool) -> std::io::Result<kioto_serial::SerialStream> {
+    let builder = kioto_serial::new(path, 115_200).exclusive(exclusive);
+    kioto_serial::SerialPortBuilderExt::open_native_async(builder)
+}
+
+#[test]
+fn an_exclusive_port_cannot_be_opened_again() {
+    let (_master, path) = pty();
+    let _stream = open(&path, true).unwrap();
+    assert!(open(&path, false).is_err());
+    assert!(open(&path, true).is_err());
+}
+
+#[test]
+fn a_shared_port_can_be_opened_again() {
+    let (_master, path) = pty();
+    let _stream = open(&path, false).unwrap();
+    let _other = open(&path, false).unwrap();
+    assert!(open(&path, true).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn exclusive_access_can_be_claimed_and_released() {
+    let (_master, path) = pty();
+    let stream = open(&path, false).unwrap();
+    let is_exclusive = || {
+        let mut exclusive: libc::c_int = 0;
+        // SAFETY: `TIOCGEXCL` writes an int to the pointer.
+        let res = unsafe { libc::ioctl(stream.raw_fd().unwrap(), libc::TIOCGEXCL, &mut exclusive) };
+        assert_eq!(res, 0);
+        exclusive != 0
+    };
+    assert!(!is_exclusive());
+    stream.set_exclusive(true).unwrap();
+    assert!(is_exclusive());
+    stream.set_exclusive(false).unwrap();
+    assert!(!is_exclusive());
+}
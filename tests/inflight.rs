@@ -0,0 +1,46 @@
+//! The bytes written but not handed to the port can be limited.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use kioto_serial::FlowControl;
+use tokio::io::AsyncWriteExt;
+
+use common::{block_on, open_pty_with, read_exact};
+
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+#[test]
+fn writing_waits_once_the_limit_is_reached() {
+    let (mut stream, mut master) = open_pty_with(|builder| {
+        builder
+            .flow_control(FlowControl::Software)
+            .max_buf_size(4096)
+            .max_inflight_write(100)
+    });
+    // Stop the output of the stream, so that the writer thread cannot hand
+    // on bytes.
+    master.write_all(&[XOFF]).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    let message: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let accepted = block_on(async {
+        let mut accepted = 0;
+        while let Ok(res) = tokio::time::timeout(
+            Duration::from_millis(100),
+            stream.write(&message[accepted..]),
+        )
+        .await
+        {
+            accepted += res.unwrap();
+        }
+        accepted
+    });
+    assert!(accepted > 0);
+    assert!(accepted <= 100, "{accepted} bytes were accepted");
+    master.write_all(&[XON]).unwrap();
+    assert_eq!(read_exact(&mut master, accepted), &message[..accepted]);
+}
@@ -0,0 +1,30 @@
+//! `VMIN` holds back received bytes until enough of them have arrived.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn reads_wait_for_vmin_bytes() {
+    let (mut stream, mut master) = open_pty();
+    stream.set_inter_byte_timeout(4, 0).unwrap();
+    block_on(async {
+        let mut buf = [0u8; 4];
+        master.write_all(b"ab").unwrap();
+        tokio::time::timeout(Duration::from_millis(100), stream.read(&mut buf))
+            .await
+            .expect_err("bytes were handed on before VMIN was reached");
+        master.write_all(b"cd").unwrap();
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"abcd");
+    });
+}
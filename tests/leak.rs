@@ -0,0 +1,43 @@
+//! Opening and dropping streams does not leak threads.
+//!
+//! This is the only test in its binary, so that no other test starts threads
+//! meanwhile.
+#![cfg(target_os = "linux")]
+
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+/// The number of threads of this process.
+fn thread_count() -> usize {
+    std::fs::read_dir("/proc/self/task").unwrap().count()
+}
+
+#[test]
+fn open_and_drop_many_times() {
+    let (_master, slave) = serialport::TTYPort::pair().unwrap();
+    let path = slave.name().unwrap();
+    drop(slave);
+    let before = thread_count();
+    let mut peak = before;
+    for _ in 0..10_000 {
+        let stream = kioto_serial::SerialPortBuilderExt::open_native_async(
+            kioto_serial::new(&path, 115_200).exclusive(false),
+        )
+        .unwrap();
+        drop(stream);
+        peak = peak.max(thread_count());
+    }
+    // The reader threads exit within about 100 milliseconds of the drop.
+    let start = std::time::Instant::now();
+    while thread_count() > before {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "{} threads left running",
+            thread_count() - before
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    // Threads exit about as fast as they are started.
+    assert!(peak < before + 500, "up to {} threads", peak - before);
+}
@@ -0,0 +1,36 @@
+//! In loopback mode, written bytes are read back instead of transmitted.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use serialport::SerialPort;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, open_pty_with, PROMPT};
+
+#[test]
+fn written_bytes_are_read_back() {
+    let (mut stream, mut master) = open_pty_with(|builder| builder.loopback(true));
+    block_on(async {
+        stream.write_all(b"self test").await.unwrap();
+        tokio::time::timeout(PROMPT, stream.flush())
+            .await
+            .expect("flushing was blocked")
+            .unwrap();
+        let mut buf = [0u8; 9];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"self test");
+        assert_eq!(master.bytes_to_read().unwrap(), 0);
+
+        // Bytes arriving at the port stay there.
+        master.write_all(b"line").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(stream.bytes_to_read().unwrap(), 4);
+    });
+}
@@ -0,0 +1,49 @@
+//! A mock stream delivers its schedule and collects what is written.
+#![cfg(all(feature = "test-util", not(target_os = "windows")))]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use kioto_serial::MockSerial;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, PROMPT};
+
+#[test]
+fn the_schedule_arrives_in_time() {
+    let start = Instant::now();
+    let (mut stream, _handle) = MockSerial::new(Vec::new())
+        .then(Duration::ZERO, "slow")
+        .then(Duration::from_millis(200), "arrival")
+        .open()
+        .unwrap();
+    block_on(async {
+        let mut buf = [0u8; 11];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"slowarrival");
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        // The line stays idle once the schedule is exhausted.
+        tokio::time::timeout(Duration::from_millis(100), stream.read(&mut buf))
+            .await
+            .expect_err("more bytes arrived");
+    });
+}
+
+#[test]
+fn writes_and_control_lines_are_captured() {
+    let (mut stream, mut handle) = MockSerial::default().open().unwrap();
+    block_on(async {
+        stream.write_all(b"AT\r").await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(handle.take_written().unwrap(), b"AT\r");
+        assert!(handle.take_written().unwrap().is_empty());
+    });
+    stream.write_request_to_send(true).unwrap();
+    stream.write_data_terminal_ready(false).unwrap();
+    assert!(handle.request_to_send().unwrap());
+    assert!(!handle.data_terminal_ready().unwrap());
+}
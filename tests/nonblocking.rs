@@ -0,0 +1,58 @@
+//! With `nonblocking_io`, the reader thread waits for data with `poll(2)`.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty_with, PROMPT};
+
+/// How often the reader thread checks whether to stop without `poll(2)`.
+const READ_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[test]
+fn data_is_read() {
+    let (mut stream, mut master) = open_pty_with(|builder| builder.nonblocking_io(true));
+    // SAFETY: The stream keeps the descriptor open.
+    let flags = unsafe { libc::fcntl(stream.raw_fd().unwrap(), libc::F_GETFL) };
+    assert_ne!(flags & libc::O_NONBLOCK, 0);
+    block_on(async {
+        for message in [&b"first"[..], b"second"] {
+            master.write_all(message).unwrap();
+            let mut buf = vec![0u8; message.len()];
+            tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+                .await
+                .expect("reading was blocked")
+                .unwrap();
+            assert_eq!(buf, message);
+        }
+    });
+}
+
+#[test]
+fn reader_stops_promptly_on_drop() {
+    let configure =
+        |builder: kioto_serial::SerialPortBuilder| builder.exclusive(true).nonblocking_io(true);
+    let (stream, _master) = open_pty_with(configure);
+    let path = stream.name().unwrap();
+    // Let the reader thread start waiting for data.
+    std::thread::sleep(Duration::from_millis(50));
+    drop(stream);
+    // The port can be opened exclusively again once the reader thread has
+    // closed its handle.
+    let start = Instant::now();
+    let reopened = loop {
+        let builder = configure(kioto_serial::new(&path, 115_200));
+        match kioto_serial::SerialPortBuilderExt::open_native_async(builder) {
+            Ok(stream) => break stream,
+            Err(_) if start.elapsed() < PROMPT => std::thread::sleep(Duration::from_millis(1)),
+            Err(e) => panic!("the port stayed locked: {e}"),
+        }
+    };
+    let elapsed = start.elapsed();
+    drop(reopened);
+    assert!(elapsed < READ_POLL_INTERVAL / 4, "took {elapsed:?}");
+}
@@ -0,0 +1,52 @@
+//! Invalid settings make opening fail cleanly.
+#![cfg(not(target_os = "windows"))]
+
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+/// Open a pseudo-terminal with `configure` and return the error.
+fn open_error(
+    configure: impl FnOnce(kioto_serial::SerialPortBuilder) -> kioto_serial::SerialPortBuilder,
+) -> std::io::Error {
+    let (_master, slave) = serialport::TTYPort::pair().unwrap();
+    let path = slave.name().unwrap();
+    drop(slave);
+    let builder = configure(kioto_serial::new(&path, 115_200).exclusive(false));
+    kioto_serial::SerialPortBuilderExt::open_native_async(builder).unwrap_err()
+}
+
+#[test]
+fn buffer_sizes_are_checked() {
+    let configures: [fn(kioto_serial::SerialPortBuilder) -> kioto_serial::SerialPortBuilder; 3] = [
+        |builder| builder.max_buf_size(0),
+        |builder| builder.read_buf_size(0),
+        |builder| builder.write_buf_size(0),
+    ];
+    for (i, configure) in configures.into_iter().enumerate() {
+        let e = open_error(configure);
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput, "setting {i}");
+    }
+}
+
+#[test]
+fn coalescing_size_is_checked() {
+    for size in [0, 1025, usize::MAX] {
+        let e = open_error(|builder| {
+            builder
+                .read_buf_size(1024)
+                .read_coalesce(Duration::from_millis(10), size)
+        });
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput, "size {size}");
+    }
+}
+
+#[test]
+fn read_chunk_size_is_checked() {
+    let e = open_error(|builder| {
+        builder
+            .read_chunk_size(usize::MAX)
+            .read_coalesce(Duration::from_millis(10), 2)
+    });
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+}
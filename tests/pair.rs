@@ -0,0 +1,58 @@
+//! Streams created by `SerialStream::pair` are connected back to back.
+#![cfg(all(feature = "test-util", not(target_os = "windows")))]
+
+mod common;
+
+use kioto_serial::SerialStream;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, PROMPT};
+
+async fn read_exact(stream: &mut SerialStream, len: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; len];
+    tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+        .await
+        .expect("reading was blocked")
+        .unwrap();
+    buf
+}
+
+#[test]
+fn bytes_pass_both_ways() {
+    block_on(async {
+        let (mut a, mut b) = SerialStream::pair().unwrap();
+        a.write_all(b"ping").await.unwrap();
+        a.flush().await.unwrap();
+        assert_eq!(read_exact(&mut b, 4).await, b"ping");
+        b.write_all(b"pong").await.unwrap();
+        b.flush().await.unwrap();
+        assert_eq!(read_exact(&mut a, 4).await, b"pong");
+    });
+}
+
+#[test]
+fn control_lines_are_wired_as_by_a_null_modem() {
+    let (a, b) = SerialStream::pair().unwrap();
+    a.write_request_to_send(true).unwrap();
+    a.write_data_terminal_ready(false).unwrap();
+    assert!(b.read_clear_to_send().unwrap());
+    assert!(!b.read_data_set_ready().unwrap());
+    a.write_request_to_send(false).unwrap();
+    a.write_data_terminal_ready(true).unwrap();
+    assert!(!b.read_clear_to_send().unwrap());
+    assert!(b.read_data_set_ready().unwrap());
+}
+
+#[test]
+fn dropping_one_stream_breaks_the_other() {
+    block_on(async {
+        let (a, mut b) = SerialStream::pair().unwrap();
+        drop(a);
+        let mut buf = [0u8; 1];
+        let e = tokio::time::timeout(PROMPT, b.read(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe);
+    });
+}
@@ -0,0 +1,34 @@
+//! Peeked bytes are returned again by the next read.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn peeking_does_not_consume() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        master.write_all(b"\x02frame").unwrap();
+        let mut header = [0u8; 1];
+        let sz = tokio::time::timeout(PROMPT, stream.peek(&mut header))
+            .await
+            .expect("peeking was blocked")
+            .unwrap();
+        assert_eq!(&header[..sz], b"\x02");
+        // Peeking again returns the same bytes.
+        assert_eq!(stream.peek(&mut header).await.unwrap(), 1);
+        assert_eq!(&header, b"\x02");
+
+        let mut buf = [0u8; 6];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"\x02frame");
+    });
+}
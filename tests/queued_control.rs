@@ -0,0 +1,39 @@
+//! Queued control operations wait for the bytes written before them.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use kioto_serial::FlowControl;
+use tokio::io::AsyncWriteExt;
+
+use common::{block_on, open_pty_with, read_exact, PROMPT};
+
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+#[test]
+fn the_baud_rate_changes_after_earlier_bytes_were_sent() {
+    let (mut stream, mut master) =
+        open_pty_with(|builder| builder.flow_control(FlowControl::Software));
+    // Stop the output of the stream, so that the written bytes stay queued.
+    master.write_all(&[XOFF]).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    block_on(async {
+        stream.write_all(b"slow").await.unwrap();
+        let stream = &stream;
+        let (res, ()) = futures::join!(
+            tokio::time::timeout(PROMPT, stream.set_baud_rate_queued(9600)),
+            async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                assert_eq!(stream.settings().unwrap().baud_rate, 115_200);
+                master.write_all(&[XON]).unwrap();
+                assert_eq!(read_exact(&mut master, 4), b"slow");
+            },
+        );
+        res.expect("the change was blocked").unwrap();
+        assert_eq!(stream.settings().unwrap().baud_rate, 9600);
+    });
+}
@@ -0,0 +1,45 @@
+//! Bytes pass through the port unchanged.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, open_pty_with, read_exact, PROMPT};
+
+/// Every byte value, with line endings in every order.
+fn binary_data() -> Vec<u8> {
+    let mut data: Vec<u8> = (0..=255).collect();
+    data.extend_from_slice(b"\r\n\n\r\r\r\n\n");
+    data
+}
+
+fn round_trip(force_raw_mode: bool) {
+    let (mut stream, mut master) = open_pty_with(|builder| builder.force_raw_mode(force_raw_mode));
+    let data = binary_data();
+    block_on(async {
+        stream.write_all(&data).await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(read_exact(&mut master, data.len()), data);
+
+        master.write_all(&data).unwrap();
+        let mut received = vec![0; data.len()];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut received))
+            .await
+            .expect("bytes went missing")
+            .unwrap();
+        assert_eq!(received, data);
+    });
+}
+
+#[test]
+fn bytes_are_not_translated() {
+    round_trip(false);
+}
+
+#[test]
+fn bytes_are_not_translated_with_force_raw_mode() {
+    round_trip(true);
+}
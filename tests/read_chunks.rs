@@ -0,0 +1,39 @@
+//! The reader thread reads from the port in chunks of the configured size.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty_with, PROMPT};
+
+#[test]
+fn reads_are_bounded_by_read_chunk_size() {
+    let reads = Arc::new(Mutex::new(Vec::new()));
+    let (mut stream, mut master) = open_pty_with(|builder| {
+        let reads = reads.clone();
+        builder
+            .read_chunk_size(7)
+            .with_tracer(move |direction, buf| {
+                if direction == kioto_serial::Direction::Read {
+                    reads.lock().unwrap().push(buf.len());
+                }
+            })
+    });
+    let message: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    block_on(async {
+        master.write_all(&message).unwrap();
+        let mut received = vec![0u8; message.len()];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut received))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(received, message);
+    });
+    let reads = reads.lock().unwrap();
+    assert_eq!(reads.iter().sum::<usize>(), message.len());
+    assert!(reads.iter().all(|&len| len <= 7), "{reads:?}");
+}
@@ -0,0 +1,57 @@
+//! Bytes received before reading fails are still read.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use futures::StreamExt;
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty, open_pty_with, PROMPT};
+
+#[test]
+fn lines_are_read_before_a_disconnect() {
+    let (stream, mut master) = open_pty();
+    block_on(async {
+        master.write_all(b"full\npartial").unwrap();
+        // Let the reader thread receive the bytes before the hangup.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(master);
+        tokio::time::timeout(PROMPT, async {
+            while stream.worker_status().reader_alive {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("the hangup went unnoticed");
+        let lines: Vec<_> = tokio::time::timeout(PROMPT, stream.lines(1024).collect())
+            .await
+            .expect("the lines did not end");
+        let [Ok(full), Ok(partial), Err(e)] = lines.as_slice() else {
+            panic!("unexpected lines: {lines:?}");
+        };
+        assert_eq!(full, "full");
+        assert_eq!(partial, "partial");
+        assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe);
+    });
+}
+
+#[test]
+fn bytes_are_read_before_a_timeout() {
+    let (mut stream, mut master) =
+        open_pty_with(|builder| builder.timeout(Duration::from_millis(100)));
+    block_on(async {
+        master.write_all(b"abc").unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let mut buf = [0u8; 16];
+        let sz = stream.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..sz], b"abc");
+        for _ in 0..2 {
+            let err = stream.read(&mut buf).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        }
+    });
+    drop(master);
+}
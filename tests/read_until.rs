@@ -0,0 +1,44 @@
+//! Reading up to a delimiter leaves the bytes after it in the stream.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn a_delimiter_split_across_reads_is_found() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        let (res, ()) = futures::join!(
+            tokio::time::timeout(PROMPT, stream.read_until(b"OK\r\n", 64)),
+            async {
+                for piece in [&b"value O"[..], b"K\r", b"\nnext"] {
+                    master.write_all(piece).unwrap();
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+            },
+        );
+        assert_eq!(res.expect("reading was blocked").unwrap(), b"value OK\r\n");
+        let mut rest = [0u8; 4];
+        stream.read_exact(&mut rest).await.unwrap();
+        assert_eq!(&rest, b"next");
+    });
+}
+
+#[test]
+fn reading_more_than_max_fails() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        master.write_all(b"0123456789\n").unwrap();
+        let e = tokio::time::timeout(PROMPT, stream.read_until(b"\n", 8))
+            .await
+            .expect("reading was blocked")
+            .unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    });
+}
@@ -0,0 +1,37 @@
+//! A port without control lines is ready once data arrives.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn a_pseudo_terminal_is_ready_once_data_arrives() {
+    let (stream, mut master) = open_pty();
+    block_on(async {
+        tokio::time::timeout(Duration::from_millis(100), stream.ready())
+            .await
+            .expect_err("ready without data");
+        let (res, ()) = futures::join!(tokio::time::timeout(PROMPT, stream.ready()), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            master.write_all(b"hi").unwrap();
+        });
+        res.expect("readiness was not noticed").unwrap();
+    });
+}
+
+#[test]
+fn waiting_fails_once_the_device_is_gone() {
+    let (stream, master) = open_pty();
+    drop(master);
+    block_on(async {
+        let e = tokio::time::timeout(PROMPT, stream.ready())
+            .await
+            .expect("the hangup was not noticed")
+            .unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe);
+    });
+}
@@ -0,0 +1,77 @@
+//! A reconnecting stream reopens its port after the device went away.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use kioto_serial::{ReconnectPolicy, ReconnectingSerial};
+use serialport::SerialPort;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, read_exact, PROMPT};
+
+/// Create a pseudo-terminal and point `link` at it, so that opening `link`
+/// opens the new device like a USB adapter coming back under the same path.
+fn plug_in(link: &Path) -> serialport::TTYPort {
+    let (mut master, slave) = serialport::TTYPort::pair().unwrap();
+    master.set_timeout(PROMPT).unwrap();
+    let tmp = link.with_extension("new");
+    std::os::unix::fs::symlink(slave.name().unwrap(), &tmp).unwrap();
+    std::fs::rename(&tmp, link).unwrap();
+    master
+}
+
+/// Read as many bytes as `sent` while sending them after a while.
+async fn read_delayed(
+    stream: &mut ReconnectingSerial,
+    master: &mut serialport::TTYPort,
+    sent: &[u8],
+) -> Vec<u8> {
+    let mut buf = vec![0u8; sent.len()];
+    let (res, ()) = futures::join!(
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf)),
+        async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            master.write_all(sent).unwrap();
+        },
+    );
+    res.expect("reading was blocked").unwrap();
+    buf
+}
+
+#[test]
+fn the_port_is_reopened_after_a_disconnect() {
+    let dir: PathBuf =
+        std::env::temp_dir().join(format!("kioto-serial-reconnect-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let link = dir.join("port");
+    let mut master = plug_in(&link);
+    let builder = kioto_serial::new(link.to_str().unwrap(), 115_200).exclusive(false);
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(20),
+        max_delay: Duration::from_millis(50),
+        max_attempts: None,
+    };
+    let mut stream = ReconnectingSerial::new(builder, policy);
+    block_on(async {
+        // The port is opened on first use, and a pseudo-terminal takes input
+        // only while it is open.
+        assert_eq!(read_delayed(&mut stream, &mut master, b"one").await, b"one");
+
+        // Unplug the device, which hangs up the stream, and plug in another.
+        let mut replugged = plug_in(&link);
+        drop(master);
+        assert_eq!(
+            read_delayed(&mut stream, &mut replugged, b"two").await,
+            b"two"
+        );
+
+        stream.write_all(b"back").await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(read_exact(&mut replugged, 4), b"back");
+    });
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,44 @@
+//! Reopening a port restores its settings and starts with empty buffers.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use kioto_serial::{FlowControl, StopBits};
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty_with, PROMPT};
+
+#[test]
+fn reopening_keeps_the_settings_and_discards_buffered_bytes() {
+    let (mut stream, mut master) = open_pty_with(|builder| {
+        builder
+            .stop_bits(StopBits::Two)
+            .flow_control(FlowControl::Software)
+    });
+    block_on(async {
+        stream.set_baud_rate(9600).unwrap();
+        stream.set_stop_bits(StopBits::One).unwrap();
+        assert_eq!(stream.settings().unwrap().stop_bits, StopBits::One);
+        master.write_all(b"stale").unwrap();
+        // Give the reader thread time to move the bytes into the internal
+        // buffer.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stream.reopen().unwrap();
+
+        let settings = stream.settings().unwrap();
+        assert_eq!(settings.baud_rate, 115_200);
+        assert_eq!(settings.stop_bits, StopBits::Two);
+        assert_eq!(settings.flow_control, FlowControl::Software);
+
+        master.write_all(b"fresh").unwrap();
+        let mut buf = [0u8; 5];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(&buf, b"fresh");
+    });
+}
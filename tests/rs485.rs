@@ -0,0 +1,20 @@
+//! RS-485 direction control needs a port with an RTS line.
+#![cfg(not(target_os = "windows"))]
+
+use serialport::SerialPort;
+
+#[test]
+fn opening_a_port_without_rts_fails_and_releases_it() {
+    // A pseudo-terminal has no control lines, so RTS cannot be released when
+    // the port is opened.
+    let (_master, slave) = serialport::TTYPort::pair().unwrap();
+    let path = slave.name().unwrap();
+    drop(slave);
+    let builder = kioto_serial::new(&path, 115_200)
+        .exclusive(true)
+        .rs485(kioto_serial::Rs485Config::default());
+    kioto_serial::SerialPortBuilderExt::open_native_async(builder).unwrap_err();
+    // The exclusive lock was released along with the port.
+    let builder = kioto_serial::new(&path, 115_200).exclusive(true);
+    kioto_serial::SerialPortBuilderExt::open_native_async(builder).unwrap();
+}
@@ -0,0 +1,45 @@
+//! Breaks are ordered with the bytes written around them.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncWriteExt;
+
+use common::{block_on, open_pty, read_exact, PROMPT};
+
+#[test]
+fn bytes_written_during_a_break_follow_it() {
+    let (mut stream, mut master) = open_pty();
+    let received = std::thread::spawn(move || {
+        let before = read_exact(&mut master, 6);
+        let after = read_exact(&mut master, 5);
+        (master, before, after)
+    });
+    let start = Instant::now();
+    block_on(async {
+        stream.write_all(b"before").await.unwrap();
+        tokio::time::timeout(PROMPT, stream.send_break(Duration::from_millis(200)))
+            .await
+            .expect("the break was blocked")
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // Dropping the future does not cut the break short.
+        let start = Instant::now();
+        let send_break = stream.send_break(Duration::from_millis(200));
+        tokio::time::timeout(Duration::from_millis(20), send_break)
+            .await
+            .expect_err("the break ended early");
+        stream.write_all(b"after").await.unwrap();
+        tokio::time::timeout(PROMPT, stream.flush())
+            .await
+            .expect("flushing was blocked")
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    });
+    let (_master, before, after) = received.join().unwrap();
+    assert_eq!(before, b"before");
+    assert_eq!(after, b"after");
+}
@@ -0,0 +1,50 @@
+//! A set of streams yields data from whichever stream has some.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+
+use kioto_serial::SerialSet;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn data_is_yielded_with_the_index_of_its_stream() {
+    let (a, mut master_a) = open_pty();
+    let (b, mut master_b) = open_pty();
+    let mut set = SerialSet::new();
+    assert_eq!(set.push(a), 0);
+    assert_eq!(set.push(b), 1);
+    block_on(async {
+        for (master, index, message) in [
+            (&mut master_b, 1, &b"beta"[..]),
+            (&mut master_a, 0, b"alpha"),
+        ] {
+            master.write_all(message).unwrap();
+            let (i, res) = tokio::time::timeout(PROMPT, set.next_readable())
+                .await
+                .expect("reading was blocked")
+                .unwrap();
+            assert_eq!(i, index);
+            assert_eq!(&res.unwrap()[..], message);
+        }
+
+        // A stream which went away is reported once and kept in the set.
+        drop(master_a);
+        let (i, res) = tokio::time::timeout(PROMPT, set.next_readable())
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(i, 0);
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::BrokenPipe);
+        assert!(set.get(0).is_some());
+        master_b.write_all(b"still").unwrap();
+        let (i, res) = tokio::time::timeout(PROMPT, set.next_readable())
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        assert_eq!(i, 1);
+        assert_eq!(&res.unwrap()[..], b"still");
+    });
+}
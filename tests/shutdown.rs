@@ -0,0 +1,71 @@
+//! Shutting down stops the worker threads once written bytes were transmitted.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use common::{block_on, open_pty, read_exact, PROMPT};
+
+#[test]
+fn shutdown_stops_writer_thread() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        stream.write_all(b"last words").await.unwrap();
+        tokio::time::timeout(PROMPT, AsyncWriteExt::shutdown(&mut stream))
+            .await
+            .expect("shutdown stalled")
+            .unwrap();
+        // The thread reports its result just before it finishes.
+        tokio::time::timeout(PROMPT, async {
+            while stream.worker_status().writer_alive {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("the writer thread kept running");
+        assert!(stream.worker_status().reader_alive);
+        // Bytes written before shutting down were still transmitted.
+        assert_eq!(read_exact(&mut master, 10), b"last words");
+
+        let err = stream.write_all(b"more").await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        // Shutting down again succeeds.
+        AsyncWriteExt::shutdown(&mut stream).await.unwrap();
+
+        // Reading is unaffected.
+        master.write_all(b"reply").unwrap();
+        let mut buf = [0u8; 5];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf, b"reply");
+    });
+}
+
+#[test]
+fn shutdown_transmits_everything_written() {
+    let (mut stream, mut master) = open_pty();
+    // More than the pty buffers, so that bytes are still queued in the stream
+    // when shutting down.
+    let message: Vec<u8> = (0..=255).cycle().take(256 * 1024).collect();
+    let expected = message.clone();
+    // The master is handed back, as closing it would hang up the stream.
+    let received = std::thread::spawn(move || {
+        let received = read_exact(&mut master, expected.len());
+        (master, received == expected)
+    });
+    block_on(async {
+        stream.write_all(&message).await.unwrap();
+        tokio::time::timeout(PROMPT, stream.shutdown())
+            .await
+            .expect("shutdown stalled")
+            .unwrap();
+    });
+    let (_master, intact) = received.join().unwrap();
+    assert!(intact);
+}
@@ -0,0 +1,30 @@
+//! Buffered input can be taken without waiting for more.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+
+use common::{block_on, open_pty, PROMPT};
+
+#[test]
+fn received_bytes_are_taken_without_waiting() {
+    let (mut stream, mut master) = open_pty();
+    assert!(stream.take_buffered().is_empty());
+    block_on(async {
+        master.write_all(b"partial frame").unwrap();
+        let mut buf = [0u8; 8];
+        tokio::time::timeout(PROMPT, stream.read_exact(&mut buf))
+            .await
+            .expect("reading was blocked")
+            .unwrap();
+        // Give the reader thread time to move the rest into the internal
+        // buffer.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    });
+    assert_eq!(stream.take_buffered(), b"frame");
+    assert!(stream.take_buffered().is_empty());
+}
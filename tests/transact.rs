@@ -0,0 +1,59 @@
+//! A command is sent and the response read up to a delimiter.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use common::{block_on, open_pty, read_exact, PROMPT};
+
+#[test]
+fn the_response_is_read_up_to_the_delimiter() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        let (res, ()) = futures::join!(stream.transact(b"ID?\r", b"\r\n", PROMPT), async {
+            assert_eq!(read_exact(&mut master, 4), b"ID?\r");
+            master.write_all(b"kioto\r\nrest").unwrap();
+        });
+        assert_eq!(res.unwrap(), b"kioto\r\n");
+    });
+}
+
+#[test]
+fn an_incomplete_response_times_out() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        let timeout = Duration::from_millis(200);
+        let (res, ()) = futures::join!(stream.transact(b"?", b"\n", timeout), async {
+            assert_eq!(read_exact(&mut master, 1), b"?");
+            master.write_all(b"partial").unwrap();
+        });
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    });
+}
+
+#[test]
+fn stale_bytes_are_kept_unless_cleared() {
+    let (mut stream, mut master) = open_pty();
+    block_on(async {
+        master.write_all(b"old\n").unwrap();
+        // Give the reader thread time to move the bytes into the internal
+        // buffer.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (res, ()) = futures::join!(stream.transact(b"?", b"\n", PROMPT), async {
+            assert_eq!(read_exact(&mut master, 1), b"?");
+            master.write_all(b"new\n").unwrap();
+        });
+        assert_eq!(res.unwrap(), b"old\n");
+        assert_eq!(stream.read_until(b"\n", 16).await.unwrap(), b"new\n");
+
+        master.write_all(b"old\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (res, ()) = futures::join!(stream.transact_clearing(b"?", b"\n", PROMPT), async {
+            assert_eq!(read_exact(&mut master, 1), b"?");
+            master.write_all(b"new\n").unwrap();
+        });
+        assert_eq!(res.unwrap(), b"new\n");
+    });
+}
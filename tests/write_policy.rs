@@ -0,0 +1,75 @@
+//! Writes wait for room in the internal buffer or drop the oldest bytes.
+#![cfg(not(target_os = "windows"))]
+
+mod common;
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use kioto_serial::{FlowControl, WritePolicy};
+use serialport::SerialPort;
+use tokio::io::AsyncWriteExt;
+
+use common::{block_on, open_pty_with, PROMPT};
+
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+/// Open a stream whose output the other end has stopped with software flow
+/// control, so that the writer thread cannot hand on bytes.
+fn open_stopped(write_policy: WritePolicy) -> (kioto_serial::SerialStream, serialport::TTYPort) {
+    let (stream, mut master) = open_pty_with(|builder| {
+        builder
+            .flow_control(FlowControl::Software)
+            .max_buf_size(64)
+            .write_policy(write_policy)
+    });
+    master.write_all(&[XOFF]).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    (stream, master)
+}
+
+/// Read what arrives until nothing more does for a while.
+fn read_all(master: &mut serialport::TTYPort) -> Vec<u8> {
+    master.set_timeout(Duration::from_millis(200)).unwrap();
+    let mut received = Vec::new();
+    let mut buf = [0u8; 4096];
+    while let Ok(sz @ 1..) = master.read(&mut buf) {
+        received.extend_from_slice(&buf[..sz]);
+    }
+    received
+}
+
+#[test]
+fn block_applies_backpressure() {
+    let (mut stream, mut master) = open_stopped(WritePolicy::Block);
+    let message: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    block_on(async {
+        tokio::time::timeout(Duration::from_millis(200), stream.write_all(&message))
+            .await
+            .expect_err("writing did not wait");
+    });
+    master.write_all(&[XON]).unwrap();
+    // The bytes accepted before the write was cancelled arrive in order.
+    let received = read_all(&mut master);
+    assert!(!received.is_empty());
+    assert!(message.starts_with(&received));
+}
+
+#[test]
+fn drop_oldest_never_waits() {
+    let (mut stream, mut master) = open_stopped(WritePolicy::DropOldest);
+    let message: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    block_on(async {
+        tokio::time::timeout(PROMPT, stream.write_all(&message))
+            .await
+            .expect("writing waited")
+            .unwrap();
+        assert!(stream.write_buffer_len() <= 64);
+    });
+    master.write_all(&[XON]).unwrap();
+    let received = read_all(&mut master);
+    assert!(received.len() < message.len());
+    // The most recent bytes are kept.
+    assert!(received.ends_with(&message[message.len() - 32..]));
+}